@@ -0,0 +1,59 @@
+//! Command-line flags
+use std::env;
+use std::path::PathBuf;
+
+#[derive(Default, Debug)]
+pub struct CliArgs {
+    /// `--config <path>`: read the [GameConfig](crate::config::GameConfig) from `path`
+    pub config: Option<PathBuf>,
+    /// `--grid-size <i32>`: override the config's board size
+    pub grid_size: Option<i32>,
+    /// `--wrap`: play on a board whose edges wrap around instead of walls
+    pub wrap: bool,
+    /// `--level <path>`: load a level file and start on it
+    pub level: Option<PathBuf>,
+    /// `--food-count <usize>`: override how many pieces of food are on the board at once
+    pub food_count: Option<usize>,
+    /// `--tick-seconds <f32>`: override the config's movement tick
+    pub tick_seconds: Option<f32>,
+    /// `--seed <u64>`: play every game with the same food sequence
+    pub seed: Option<u64>,
+    /// `--record <path>`: write a replay of each finished game to `path`
+    pub record: Option<PathBuf>,
+    /// `--replay <path>`: play back a recorded replay instead of reading the keyboard
+    pub replay: Option<PathBuf>,
+}
+
+impl CliArgs {
+    pub fn parse() -> Self {
+        let mut cli_args = CliArgs::default();
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--seed" => match args.next().map(|v| v.parse()) {
+                    Some(Ok(seed)) => cli_args.seed = Some(seed),
+                    _ => eprintln!("--seed expects an unsigned integer"),
+                },
+                "--config" => cli_args.config = args.next().map(PathBuf::from),
+                "--grid-size" => match args.next().map(|v| v.parse()) {
+                    Some(Ok(grid_size)) => cli_args.grid_size = Some(grid_size),
+                    _ => eprintln!("--grid-size expects an integer"),
+                },
+                "--wrap" => cli_args.wrap = true,
+                "--level" => cli_args.level = args.next().map(PathBuf::from),
+                "--food-count" => match args.next().map(|v| v.parse()) {
+                    Some(Ok(food_count)) => cli_args.food_count = Some(food_count),
+                    _ => eprintln!("--food-count expects an unsigned integer"),
+                },
+                "--tick-seconds" => match args.next().map(|v| v.parse()) {
+                    Some(Ok(tick_seconds)) => cli_args.tick_seconds = Some(tick_seconds),
+                    _ => eprintln!("--tick-seconds expects a number"),
+                },
+                "--record" => cli_args.record = args.next().map(PathBuf::from),
+                "--replay" => cli_args.replay = args.next().map(PathBuf::from),
+                _ => eprintln!("Ignoring unknown argument {}", arg),
+            }
+        }
+        cli_args
+    }
+}
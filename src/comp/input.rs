@@ -0,0 +1,253 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::comp::snake::SnakeDirection;
+use crate::plugins::gamepad::res::{GamepadButton, GamepadInput};
+use crate::settings::{InputDevice, Settings};
+
+/// A player's keys, saved in the [Settings] by key name
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyBinds {
+    #[serde(with = "key_name")]
+    pub up: KeyCode,
+    #[serde(with = "key_name")]
+    pub down: KeyCode,
+    #[serde(with = "key_name")]
+    pub left: KeyCode,
+    #[serde(with = "key_name")]
+    pub right: KeyCode,
+    #[serde(with = "key_name")]
+    pub forfeit: KeyCode,
+    #[serde(with = "key_name")]
+    pub pause: KeyCode,
+    /// Plays again once a run is over
+    #[serde(with = "key_name")]
+    pub restart: KeyCode,
+}
+
+/// The things a player can bind a key to
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum KeyAction {
+    Up,
+    Down,
+    Left,
+    Right,
+    Forfeit,
+    Pause,
+    Restart,
+}
+impl KeyAction {
+    pub const ALL: [KeyAction; 7] = [
+        KeyAction::Up,
+        KeyAction::Down,
+        KeyAction::Left,
+        KeyAction::Right,
+        KeyAction::Forfeit,
+        KeyAction::Pause,
+        KeyAction::Restart,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyAction::Up => "Up",
+            KeyAction::Down => "Down",
+            KeyAction::Left => "Left",
+            KeyAction::Right => "Right",
+            KeyAction::Forfeit => "Forfeit",
+            KeyAction::Pause => "Pause",
+            KeyAction::Restart => "Restart",
+        }
+    }
+}
+
+impl KeyBinds {
+    /// Arrows, WASD, IJKL and the numpad, in player order
+    pub fn for_player(player: usize) -> Self {
+        match player {
+            1 => KeyBinds {
+                up: KeyCode::W,
+                down: KeyCode::S,
+                left: KeyCode::A,
+                right: KeyCode::D,
+                forfeit: KeyCode::Q,
+                pause: KeyCode::Tab,
+                restart: KeyCode::E,
+            },
+            2 => KeyBinds {
+                up: KeyCode::I,
+                down: KeyCode::K,
+                left: KeyCode::J,
+                right: KeyCode::L,
+                forfeit: KeyCode::U,
+                pause: KeyCode::O,
+                restart: KeyCode::Y,
+            },
+            3 => KeyBinds {
+                up: KeyCode::Numpad8,
+                down: KeyCode::Numpad5,
+                left: KeyCode::Numpad4,
+                right: KeyCode::Numpad6,
+                forfeit: KeyCode::Subtract,
+                pause: KeyCode::Add,
+                restart: KeyCode::Numpad0,
+            },
+            _ => KeyBinds {
+                up: KeyCode::Up,
+                down: KeyCode::Down,
+                left: KeyCode::Left,
+                right: KeyCode::Right,
+                forfeit: KeyCode::Back,
+                pause: KeyCode::P,
+                restart: KeyCode::R,
+            },
+        }
+    }
+}
+
+impl KeyBinds {
+    pub fn get(&self, action: KeyAction) -> KeyCode {
+        match action {
+            KeyAction::Up => self.up,
+            KeyAction::Down => self.down,
+            KeyAction::Left => self.left,
+            KeyAction::Right => self.right,
+            KeyAction::Forfeit => self.forfeit,
+            KeyAction::Pause => self.pause,
+            KeyAction::Restart => self.restart,
+        }
+    }
+
+    pub fn set(&mut self, action: KeyAction, key: KeyCode) {
+        let slot = match action {
+            KeyAction::Up => &mut self.up,
+            KeyAction::Down => &mut self.down,
+            KeyAction::Left => &mut self.left,
+            KeyAction::Right => &mut self.right,
+            KeyAction::Forfeit => &mut self.forfeit,
+            KeyAction::Pause => &mut self.pause,
+            KeyAction::Restart => &mut self.restart,
+        };
+        *slot = key;
+    }
+}
+
+impl Default for KeyBinds {
+    fn default() -> Self {
+        KeyBinds::for_player(0)
+    }
+}
+
+/// What a snake is steered with, sits on the [Snake](crate::comp::snake::Snake) entity
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum InputBinding {
+    Keyboard(KeyBinds),
+    /// The d-pad and left stick of the gamepad with this index, Start to pause, South (A) to play again
+    /// and Select to forfeit
+    Gamepad(usize),
+}
+
+impl InputBinding {
+    /// The binding `player` picked in the [Settings]
+    pub fn for_player(player: usize, settings: &Settings) -> Self {
+        match settings.device(player) {
+            InputDevice::Keyboard => InputBinding::Keyboard(settings.keys(player)),
+            InputDevice::Gamepad(gamepad) => InputBinding::Gamepad(gamepad),
+        }
+    }
+
+    /// Turns pressed this frame, in the order up, down, left, right
+    pub fn turns(&self, keyboard: &Input<KeyCode>, gamepads: &GamepadInput) -> Vec<SnakeDirection> {
+        let directions = [
+            SnakeDirection::Up,
+            SnakeDirection::Down,
+            SnakeDirection::Left,
+            SnakeDirection::Right,
+        ];
+        match self {
+            InputBinding::Keyboard(keys) => {
+                let pressed = [keys.up, keys.down, keys.left, keys.right];
+                directions
+                    .iter()
+                    .zip(pressed.iter())
+                    .filter(|(_, key)| keyboard.just_pressed(**key))
+                    .map(|(direction, _)| *direction)
+                    .collect()
+            }
+            InputBinding::Gamepad(gamepad) => directions
+                .iter()
+                .filter(|direction| gamepads.just_pressed(*gamepad, GamepadButton::Turn(**direction)))
+                .copied()
+                .collect(),
+        }
+    }
+
+    pub fn pause_pressed(&self, keyboard: &Input<KeyCode>, gamepads: &GamepadInput) -> bool {
+        match self {
+            InputBinding::Keyboard(keys) => keyboard.just_pressed(keys.pause),
+            InputBinding::Gamepad(gamepad) => gamepads.just_pressed(*gamepad, GamepadButton::Pause),
+        }
+    }
+
+    /// Whether the player asked to play again, after a run is over
+    pub fn restart_pressed(&self, keyboard: &Input<KeyCode>, gamepads: &GamepadInput) -> bool {
+        match self {
+            InputBinding::Keyboard(keys) => keyboard.just_pressed(keys.restart),
+            InputBinding::Gamepad(gamepad) => gamepads.just_pressed(*gamepad, GamepadButton::Restart),
+        }
+    }
+
+    pub fn forfeit_pressed(&self, keyboard: &Input<KeyCode>, gamepads: &GamepadInput) -> bool {
+        match self {
+            InputBinding::Keyboard(keys) => keyboard.just_pressed(keys.forfeit),
+            InputBinding::Gamepad(gamepad) => gamepads.just_pressed(*gamepad, GamepadButton::Forfeit),
+        }
+    }
+}
+
+/// Saves a [KeyCode] as its name, since Bevy's key codes can't be serialized themselves
+mod key_name {
+    use bevy::prelude::KeyCode;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    /// Every key that can be bound, looked up by name when loading
+    const KEYS: [KeyCode; 98] = [
+        KeyCode::A, KeyCode::B, KeyCode::C, KeyCode::D, KeyCode::E, KeyCode::F, KeyCode::G,
+        KeyCode::H, KeyCode::I, KeyCode::J, KeyCode::K, KeyCode::L, KeyCode::M, KeyCode::N,
+        KeyCode::O, KeyCode::P, KeyCode::Q, KeyCode::R, KeyCode::S, KeyCode::T, KeyCode::U,
+        KeyCode::V, KeyCode::W, KeyCode::X, KeyCode::Y, KeyCode::Z,
+        KeyCode::Key0, KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4,
+        KeyCode::Key5, KeyCode::Key6, KeyCode::Key7, KeyCode::Key8, KeyCode::Key9,
+        KeyCode::Numpad0, KeyCode::Numpad1, KeyCode::Numpad2, KeyCode::Numpad3, KeyCode::Numpad4,
+        KeyCode::Numpad5, KeyCode::Numpad6, KeyCode::Numpad7, KeyCode::Numpad8, KeyCode::Numpad9,
+        KeyCode::Add, KeyCode::Subtract, KeyCode::Multiply, KeyCode::Divide, KeyCode::Decimal,
+        KeyCode::NumpadEnter, KeyCode::NumpadComma, KeyCode::NumpadEquals,
+        KeyCode::Up, KeyCode::Down, KeyCode::Left, KeyCode::Right,
+        KeyCode::Home, KeyCode::End, KeyCode::PageUp, KeyCode::PageDown, KeyCode::Insert, KeyCode::Delete,
+        KeyCode::Space, KeyCode::Return, KeyCode::Tab, KeyCode::Back,
+        KeyCode::LShift, KeyCode::RShift, KeyCode::LControl, KeyCode::RControl, KeyCode::LAlt, KeyCode::RAlt,
+        KeyCode::Comma, KeyCode::Period, KeyCode::Semicolon, KeyCode::Colon, KeyCode::Apostrophe,
+        KeyCode::Slash, KeyCode::Backslash, KeyCode::LBracket, KeyCode::RBracket, KeyCode::Minus,
+        KeyCode::Equals, KeyCode::Grave,
+        KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4, KeyCode::F5, KeyCode::F6,
+        KeyCode::F7, KeyCode::F8, KeyCode::F9, KeyCode::F10, KeyCode::F11, KeyCode::F12,
+    ];
+
+    /// Whether `key` can be saved, and so bound
+    pub fn is_bindable(key: KeyCode) -> bool {
+        KEYS.contains(&key)
+    }
+
+    pub fn serialize<S: Serializer>(key: &KeyCode, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{:?}", key))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<KeyCode, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        KEYS.iter()
+            .find(|key| format!("{:?}", key) == name)
+            .copied()
+            .ok_or_else(|| de::Error::custom(format!("unknown key {}", name)))
+    }
+}
+
+pub use key_name::is_bindable;
@@ -1,3 +1,4 @@
+pub mod input;
 pub mod snake;
 
 /// Tag used to enable snake movement
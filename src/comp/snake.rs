@@ -1,32 +1,93 @@
 use bevy::prelude::*;
-use std::collections::LinkedList;
+use std::collections::VecDeque;
 use serde::{Serialize, Deserialize};
+use crate::plugins::game_state::events::{GameOverReason, RunStats};
+
+/// How many turns a snake remembers ahead of its movement ticks
+pub const TURN_QUEUE_LEN: usize = 3;
 
 #[derive(Debug)]
 pub struct Snake {
-    pub body: LinkedList<Entity>,
+    /// Index into [SnakeWorld::snakes](crate::sim::SnakeWorld::snakes)
+    pub player: usize,
+    /// Segment entities, head at the front and tail at the back, each with a [SegmentOf] pointing here
+    pub body: VecDeque<Entity>,
+    /// The way the snake moved on its last step
     pub direction: SnakeDirection,
-    pub last_direction: SnakeDirection,
+    /// Turns pressed since, taken one per step
+    pub turns: VecDeque<SnakeDirection>,
+    /// Why and how the snake's run ended, set as soon as it's out even if other snakes play on
+    pub out: Option<(GameOverReason, RunStats)>,
+    /// Seconds between this snake's steps, following the [SpeedCurve](crate::sim::SpeedCurve)
+    pub step_seconds: f32,
+    /// [RunClock](crate::plugins::game_state::res::RunClock) time of the snake's next step
+    pub next_step: f32,
+    /// What a speed-up or slow-down food multiplies `step_seconds` by, and the run time it wears off
+    pub speed_effect: Option<(f32, f32)>,
 }
 
 impl Default for Snake {
     fn default() -> Self {
         Snake {
-            body: LinkedList::new(),
+            player: 0,
+            body: VecDeque::new(),
             direction: SnakeDirection::Up,
-            last_direction: SnakeDirection::Up
+            turns: VecDeque::new(),
+            out: None,
+            step_seconds: crate::constants::TICK_SECONDS,
+            next_step: crate::constants::TICK_SECONDS,
+            speed_effect: None,
         }
     }
 }
 
-#[derive(Debug, Copy, Clone, Property, Serialize, Deserialize)]
+impl Snake {
+    /// Queues a turn for a coming tick.
+    ///
+    /// The turn is checked against the one queued before it, so Up then Left from heading Right is two
+    /// valid turns. It's dropped if it would reverse onto that direction, repeat it, or overflow the queue.
+    pub fn queue_turn(&mut self, direction: SnakeDirection) -> bool {
+        let previous = self.turns.back().copied().unwrap_or(self.direction);
+        if self.turns.len() >= TURN_QUEUE_LEN || direction == previous || direction.is_opposite(previous) {
+            return false;
+        }
+        self.turns.push_back(direction);
+        true
+    }
+
+    /// The direction to move in this tick, using up the oldest queued turn
+    pub fn next_direction(&mut self) -> SnakeDirection {
+        self.turns.pop_front().unwrap_or(self.direction)
+    }
+}
+
+/// Points a snake has earned in the current game
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Score(pub u32);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Property, Serialize, Deserialize)]
 pub enum SnakeDirection {
     Up,
     Down,
     Left,
     Right,
 }
+impl SnakeDirection {
+    /// The direction pointing straight back
+    pub fn opposite(self) -> SnakeDirection {
+        match self {
+            SnakeDirection::Up => SnakeDirection::Down,
+            SnakeDirection::Down => SnakeDirection::Up,
+            SnakeDirection::Left => SnakeDirection::Right,
+            SnakeDirection::Right => SnakeDirection::Left,
+        }
+    }
+
+    pub fn is_opposite(self, other: SnakeDirection) -> bool {
+        self.opposite() == other
+    }
+}
 
-pub struct SnakeHead;
-pub struct SnakeTail;
-pub struct SnakeBody;
\ No newline at end of file
+/// Points a segment back at the [Snake] entity it belongs to
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SegmentOf(pub Entity);
\ No newline at end of file
@@ -0,0 +1,161 @@
+//! Game settings that can change without recompiling
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use crate::cli::CliArgs;
+use crate::constants;
+use crate::sim::{Arena, FoodRules, SpeedCurve};
+
+/// Seconds allowed per run in [GameMode::TimeAttack]
+pub const TIME_ATTACK_SECONDS: f32 = 60.0;
+
+/// Rule sets picked from the menu
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameMode {
+    Classic,
+    /// Score as much as possible before [TIME_ATTACK_SECONDS] run out
+    TimeAttack,
+    /// Play through the [Levels::campaign](crate::sim::Levels::campaign), meeting each level's goal to unlock the next
+    Campaign,
+}
+impl GameMode {
+    pub const ALL: [GameMode; 3] = [GameMode::Classic, GameMode::TimeAttack, GameMode::Campaign];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GameMode::Classic => "Classic",
+            GameMode::TimeAttack => "Time attack",
+            GameMode::Campaign => "Campaign",
+        }
+    }
+}
+
+impl Default for GameMode {
+    fn default() -> Self {
+        GameMode::Classic
+    }
+}
+
+/// Local players are limited by how many key sets fit on one keyboard
+pub const MAX_PLAYERS: usize = 4;
+
+/// Loaded from this file in the working directory when `--config` isn't passed
+pub const DEFAULT_CONFIG_PATH: &str = "config.ron";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameConfig {
+    /// Playable cells span `-grid_size..=grid_size` on both axes
+    pub grid_size: i32,
+    /// Snakes leaving one edge of the board come back in on the opposite edge, and there are no walls
+    pub wrap_around: bool,
+    /// Name of the [Level](crate::sim::Level) to play, replacing the open board and its `grid_size`
+    pub level: Option<String>,
+    /// Size of one cell in pixels
+    pub grid_unit: f32,
+    /// Seconds between a snake's steps at the start of a run
+    pub tick_seconds: f32,
+    /// How each snake's steps speed up from `tick_seconds`
+    pub speed: SpeedCurve,
+    /// How much food is out at once, which kinds turn up, and for how long
+    pub food: FoodRules,
+    /// Length of the countdown before each run
+    pub pre_game_seconds: f32,
+    /// Mark which way the snake will set off during the countdown
+    pub show_start_direction: bool,
+    /// Play every game with the same food sequence
+    pub seed: Option<u64>,
+    pub mode: GameMode,
+    /// Number of local players, each with their own snake
+    pub players: usize,
+    /// End the run after this many seconds, overriding the [GameMode]'s limit
+    pub time_limit: Option<f32>,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            grid_size: constants::GRID_SIZE,
+            wrap_around: false,
+            level: None,
+            grid_unit: constants::GRID_UNIT,
+            tick_seconds: constants::TICK_SECONDS,
+            speed: SpeedCurve::default(),
+            food: FoodRules::default(),
+            pre_game_seconds: constants::PRE_GAME_SECONDS,
+            show_start_direction: true,
+            seed: None,
+            mode: GameMode::default(),
+            players: 1,
+            time_limit: None,
+        }
+    }
+}
+
+impl GameConfig {
+    pub fn arena(&self) -> Arena {
+        Arena {
+            wraps: self.wrap_around,
+            ..Arena::square(self.grid_size)
+        }
+    }
+
+    /// Seconds a run may last, if limited at all
+    pub fn time_limit(&self) -> Option<f32> {
+        match self.mode {
+            GameMode::Classic | GameMode::Campaign => self.time_limit,
+            GameMode::TimeAttack => self.time_limit.or(Some(TIME_ATTACK_SECONDS)),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<GameConfig, Box<dyn Error>> {
+        let contents = fs::read_to_string(path)?;
+        Ok(ron::de::from_str(&contents)?)
+    }
+
+    /// Reads the config file named on the command line (or [DEFAULT_CONFIG_PATH]) and applies any flag overrides
+    pub fn from_args(args: &CliArgs) -> GameConfig {
+        let mut config = match &args.config {
+            Some(path) => GameConfig::load(path)
+                .unwrap_or_else(|e| panic!("Could not load config {}: {}", path.display(), e)),
+            None => {
+                let path = Path::new(DEFAULT_CONFIG_PATH);
+                if path.exists() {
+                    GameConfig::load(path).unwrap_or_else(|e| {
+                        eprintln!("Ignoring invalid config {}: {}", path.display(), e);
+                        GameConfig::default()
+                    })
+                } else {
+                    GameConfig::default()
+                }
+            }
+        };
+
+        if let Some(grid_size) = args.grid_size {
+            config.grid_size = grid_size;
+        }
+        if args.wrap {
+            config.wrap_around = true;
+        }
+        if let Some(food_count) = args.food_count {
+            config.food.count = food_count;
+        }
+        if let Some(tick_seconds) = args.tick_seconds {
+            config.tick_seconds = tick_seconds;
+        }
+        if args.seed.is_some() {
+            config.seed = args.seed;
+        }
+        // the smallest board still fits a starting snake
+        config.grid_size = config.grid_size.max(1);
+        if config.tick_seconds.is_nan() || config.tick_seconds <= 0.0 {
+            eprintln!("Ignoring tick_seconds {}, it has to be above 0", config.tick_seconds);
+            config.tick_seconds = constants::TICK_SECONDS;
+        }
+        config.players = config.players.max(1).min(MAX_PLAYERS);
+        config.food.count = config.food.count.max(1);
+        config
+    }
+}
@@ -1,2 +1,5 @@
 pub const GRID_SIZE: i32 = 3;
-pub const GRID_UNIT: f32 = 30.0;
\ No newline at end of file
+pub const GRID_UNIT: f32 = 30.0;
+pub const TICK_SECONDS: f32 = 0.3;
+pub const PRE_GAME_SECONDS: f32 = 3.0;
+pub const UI_FONT: &str = "assets/fonts/DejaVuSans-Bold.ttf";
\ No newline at end of file
@@ -0,0 +1,11 @@
+//! Gameplay events that aren't tied to a game phase
+use bevy::prelude::*;
+
+use crate::sim::{FoodKind, GridPosition};
+
+/// Event fired when a snake eats a piece of food
+pub struct FoodEatenEvent {
+    pub snake: Entity,
+    pub position: GridPosition,
+    pub kind: FoodKind,
+}
@@ -1,488 +1,855 @@
 use bevy::prelude::*;
-use rand::Rng;
-use std::collections::{HashSet, LinkedList};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+mod cli;
+mod config;
 mod constants;
 mod comp;
+mod events;
+mod persist;
 mod plugins;
+mod settings;
+mod sim;
+mod ui;
 
+use comp::input::InputBinding;
 use comp::snake::*;
+use config::{GameConfig, GameMode};
+use sim::{Arena, FoodItem, FoodKind, GameRng, GridPosition, Level, Levels, Replay, SimSnake, SnakeWorld, StepOutcome};
 
 fn main() {
+    let args = cli::CliArgs::parse();
+    let mut config = GameConfig::from_args(&args);
+
+    let mut levels = Levels::builtin();
+    if let Some(path) = &args.level {
+        let level = Level::load(path)
+            .unwrap_or_else(|e| panic!("Could not load level {}: {}", path.display(), e));
+        config.level = Some(level.name.clone());
+        levels.0.retain(|other| other.name != level.name);
+        levels.0.push(level);
+    }
+
+    let replay_mode = if let Some(path) = args.replay {
+        let replay = Replay::load(&path)
+            .unwrap_or_else(|e| panic!("Could not load replay {}: {}", path.display(), e));
+        replay.apply_to(&mut config);
+        config.players = config.players.max(1).min(config::MAX_PLAYERS);
+        ReplayMode::Playing(replay)
+    } else if let Some(path) = args.record {
+        let replay = Replay::new(0, &config, config.players);
+        ReplayMode::Recording { path, replay }
+    } else {
+        ReplayMode::Off
+    };
+
     App::build()
         .add_default_plugins()
         .add_plugin(plugins::game_state::GameStatePlugin)
-        .add_resource(SnakeMovementTimer(Timer::from_seconds(0.3, false)))
-        .add_resource(FreeLocations(HashSet::new()))
-        .add_resource(PreGameStartListenerState::default())
-        .add_resource(PreGameEndListenerState::default())
-        .add_resource(RunningGameStartListenerState::default())
+        .add_plugin(plugins::gamepad::GamepadPlugin)
+        .add_plugin(plugins::hud::HudPlugin)
+        .add_plugin(plugins::high_scores::HighScorePlugin)
+        .add_plugin(plugins::pause::PausePlugin)
+        .add_plugin(plugins::menu::MenuPlugin)
+        .add_plugin(plugins::campaign::CampaignPlugin)
+        .add_event::<events::FoodEatenEvent>()
+        .add_resource(SnakeWorld::new(config.arena(), config.players))
+        .add_resource(GameRng::new(config.seed))
+        .add_resource(config)
+        .add_resource(levels)
+        .add_resource(settings::Settings::load_or_default())
+        .add_resource(replay_mode)
+        .add_resource(PhaseChangedListenerState::default())
         .add_resource(RunningGameEndListenerState::default())
-        .add_resource(PostGameEndListenerState::default())
+        .add_resource(RunEndSent::default())
         .add_startup_system(setup.system())
         .add_system(snake_movement_system.system())
         .add_system(player_input_system.system())
-        .add_system(snake_collision_system.system())
+        .add_system(time_limit_system.system())
+        .add_system(forfeit_system.system())
+        .add_system(run_end_system.system())
+        .add_system(grid_translation_system.system())
         // .add_system(debug_food_sprite_system.system())
-        .add_system(process_running_start_events.system())
-        .add_system(process_pre_start_events.system())
-        .add_system(process_pre_end_events.system())
-        .add_system(process_post_end_events.system())
+        .add_system(process_phase_changes.system())
         .add_system(process_running_end_events.system())
         .run();
 }
 
-struct SnakeMovementTimer(Timer); // make this part of the snek?
-
-struct FreeLocations(HashSet<GridPosition>);
-
-struct KeyBinds {
-    up: KeyCode,
-    down: KeyCode,
-    left: KeyCode,
-    right: KeyCode,
+/// Whether runs are being recorded to, or played back from, a [Replay]
+enum ReplayMode {
+    Off,
+    Recording { path: PathBuf, replay: Replay },
+    Playing(Replay),
 }
 
+/// Snake colours in player order
+const PLAYER_COLORS: [Color; 4] = [
+    Color::WHITE,
+    Color::rgb(0.3, 0.9, 0.3),
+    Color::rgb(0.3, 0.6, 1.0),
+    Color::rgb(1.0, 0.5, 0.2),
+];
+
 struct Food;
 
-#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
-struct GridPosition {
-    x: i32,
-    y: i32,
-}
-impl GridPosition {
-    pub fn new(x: i32, y: i32) -> GridPosition {
-        GridPosition { x, y }
-    }
-}
+struct Wall;
 
-#[derive(Default)]
-struct RunningGameStartListenerState {
-    event_reader: EventReader<plugins::game_state::events::RunningGameStartEvent>
-}
+/// Points the way the snake will set off, shown during the countdown
+struct StartMarker;
 
-fn process_running_start_events(
-    mut commands: Commands,
-    mut state: ResMut<RunningGameStartListenerState>,
-    run_start_events: Res<Events<plugins::game_state::events::RunningGameStartEvent>>,
-    mut snake_query: Query<(&Snake, Entity)>
-) {
-    for _ in state.event_reader.iter(&run_start_events) {
-        for (_, e) in &mut snake_query.iter() {
-            commands.insert_one(e, comp::Acting);
-        }
-    }
-}
-
-fn setup(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
-    commands.spawn(Camera2dComponents::default());
-
-    // walls
-    let grid_size_float = constants::GRID_SIZE as f32;
-    let wall_length = (grid_size_float * 2.0 + 3.0) * constants::GRID_UNIT;
-    commands.spawn(SpriteComponents {
-        material: materials.add(Color::BLACK.into()),
-        translation: Translation(Vec3::new(-(constants::GRID_SIZE + 1) as f32 * constants::GRID_UNIT, 0.0, 0.0)),
-        sprite: Sprite {
-            size: Vec2::new(constants::GRID_UNIT, wall_length),
-            ..Default::default()
-        },
-        ..Default::default()
-    });
-    commands.spawn(SpriteComponents {
-        material: materials.add(Color::BLACK.into()),
-        translation: Translation(Vec3::new((constants::GRID_SIZE + 1) as f32 * constants::GRID_UNIT, 0.0, 0.0)),
-        sprite: Sprite {
-            size: Vec2::new(constants::GRID_UNIT, wall_length),
-            ..Default::default()
-        },
-        ..Default::default()
-    });
-    commands.spawn(SpriteComponents {
-        material: materials.add(Color::BLACK.into()),
-        translation: Translation(Vec3::new(0.0, -(constants::GRID_SIZE + 1) as f32 * constants::GRID_UNIT, 0.0)),
-        sprite: Sprite {
-            size: Vec2::new(wall_length, constants::GRID_UNIT),
-            ..Default::default()
-        },
-        ..Default::default()
-    });
-    commands.spawn(SpriteComponents {
-        material: materials.add(Color::BLACK.into()),
-        translation: Translation(Vec3::new(0.0, (constants::GRID_SIZE + 1) as f32 * constants::GRID_UNIT, 0.0)),
-        sprite: Sprite {
-            size: Vec2::new(wall_length, constants::GRID_UNIT),
-            ..Default::default()
-        },
-        ..Default::default()
-    });
+fn setup(mut commands: Commands) {
+    commands
+        .spawn(Camera2dComponents::default())
+        .spawn(UiCameraComponents::default());
 }
 
-fn spawn_game_entities(
+/// Spawns the wall sprites one cell outside the arena on each side
+fn spawn_walls(
     commands: &mut Commands,
+    config: &GameConfig,
     materials: &mut ResMut<Assets<ColorMaterial>>,
-    mut free_locations: &mut ResMut<FreeLocations>,
+    arena: Arena,
 ) {
-    let mut snake_entity_list = LinkedList::new();
-    let tail_pos = GridPosition::new(0, 0);
-    let body_pos = GridPosition::new(0, 1);
-    let head_pos = GridPosition::new(0, 2);
-    let food_pos = GridPosition::new(-3, 2);
-    snake_entity_list.push_front(
+    let unit = config.grid_unit;
+    for wall in arena.wall_spans().iter() {
         commands
             .spawn(SpriteComponents {
-                material: materials.add(Color::WHITE.into()),
-                translation: Translation(Vec3::new(0.0, 0.0, 0.0)),
+                material: materials.add(Color::BLACK.into()),
+                translation: Translation(Vec3::new(wall.center.0 * unit, wall.center.1 * unit, 0.0)),
                 sprite: Sprite {
-                    size: Vec2::new(constants::GRID_UNIT, constants::GRID_UNIT),
+                    size: Vec2::new(wall.size.0 * unit, wall.size.1 * unit),
                     ..Default::default()
                 },
                 ..Default::default()
             })
-            .with(SnakeTail)
-            .with(tail_pos)
+            .with(Wall);
+    }
+}
+
+fn spawn_game_entities(
+    commands: &mut Commands,
+    config: &GameConfig,
+    settings: &settings::Settings,
+    levels: &Levels,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    world: &mut ResMut<SnakeWorld>,
+    rng: &mut ResMut<GameRng>,
+) {
+    rng.start_game();
+
+    let level = config.level.as_ref().and_then(|name| {
+        let level = levels.find(name);
+        if level.is_none() {
+            eprintln!("Unknown level {}, playing on the open board", name);
+        }
+        level
+    });
+    world.arena = config.arena();
+    world.food_rules = config.food;
+    world.goal = level.filter(|_| config.mode == GameMode::Campaign).and_then(|level| level.goal);
+    match level {
+        Some(level) => {
+            world.load_level(level, config.players);
+            for pos in level.obstacles.iter() {
+                spawn_obstacle(commands, config, materials, *pos);
+            }
+        }
+        None => world.reset(config.players),
+    }
+    world.top_up_food(0.0, rng);
+
+    if !world.arena.wraps {
+        spawn_walls(commands, config, materials, world.arena);
+    }
+
+    for (player, sim_snake) in world.snakes.iter().enumerate() {
+        // the snake entity comes first so its segments can point back at it
+        let snake_entity = commands
+            .spawn((Score::default(),))
+            .with(InputBinding::for_player(player, settings))
             .current_entity()
-            .unwrap(),
-    );
+            .unwrap();
 
-    snake_entity_list.push_front(
-        commands
-            .spawn(SpriteComponents {
-                material: materials.add(Color::WHITE.into()),
-                translation: Translation(Vec3::new(0.0, constants::GRID_UNIT, 0.0)),
-                sprite: Sprite {
-                    size: Vec2::new(constants::GRID_UNIT, constants::GRID_UNIT),
-                    ..Default::default()
-                },
+        let mut snake_entity_list = VecDeque::new();
+        for pos in sim_snake.body.iter() {
+            let segment = spawn_segment(commands, config, materials, snake_entity, PLAYER_COLORS[player], *pos);
+            snake_entity_list.push_back(segment);
+        }
+
+        commands.insert_one(
+            snake_entity,
+            Snake {
+                player,
+                body: snake_entity_list,
+                direction: sim_snake.direction,
+                turns: VecDeque::new(),
+                out: None,
+                step_seconds: config.tick_seconds,
+                next_step: config.tick_seconds,
+                speed_effect: None,
+            },
+        );
+
+        if config.show_start_direction {
+            spawn_start_marker(commands, config, materials, sim_snake);
+        }
+    }
+
+    for food in world.food.iter() {
+        spawn_food(commands, config, materials, *food);
+    }
+}
+
+/// Spawns the sprite for a piece of food, coloured and sized by its kind
+fn spawn_food(
+    commands: &mut Commands,
+    config: &GameConfig,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    food: FoodItem,
+) {
+    let (color, scale) = match food.kind {
+        FoodKind::Normal => (Color::WHITE, 0.5),
+        FoodKind::Golden => (ui::HIGHLIGHT_COLOR, 0.7),
+        FoodKind::Shrink => (Color::rgb(0.7, 0.3, 0.9), 0.5),
+        FoodKind::SpeedUp => (Color::rgb(1.0, 0.3, 0.3), 0.5),
+        FoodKind::SlowDown => (Color::rgb(0.3, 0.6, 1.0), 0.5),
+        FoodKind::MultiGrow => (Color::rgb(0.3, 0.9, 0.3), 0.7),
+    };
+    commands
+        .spawn(SpriteComponents {
+            material: materials.add(color.into()),
+            translation: grid_translation(config, food.pos),
+            sprite: Sprite {
+                size: Vec2::new(config.grid_unit * scale, config.grid_unit * scale),
                 ..Default::default()
-            })
-            .with(SnakeBody)
-            .with(body_pos)
-            .current_entity()
-            .unwrap(),
-    );
+            },
+            ..Default::default()
+        })
+        .with(Food)
+        .with(food.kind)
+        .with(food.pos);
+}
 
-    snake_entity_list.push_front(
-        commands
-            .spawn(SpriteComponents {
-                material: materials.add(Color::WHITE.into()),
-                translation: Translation(Vec3::new(0.0, constants::GRID_UNIT * 2.0, 0.0)),
-                sprite: Sprite {
-                    size: Vec2::new(constants::GRID_UNIT, constants::GRID_UNIT),
-                    ..Default::default()
-                },
+/// Despawns the sprites of food that's been eaten or has expired and spawns sprites for new food,
+/// so there's one sprite for every piece of food in the [SnakeWorld]
+fn sync_food_sprites(
+    commands: &mut Commands,
+    config: &GameConfig,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    food_query: &mut Query<(&Food, Entity, &FoodKind, &GridPosition)>,
+    food: &[FoodItem],
+) {
+    let mut unshown = food.to_vec();
+    for (_food, food_entity, kind, pos) in &mut food_query.iter() {
+        match unshown.iter().position(|food| food.pos == *pos && food.kind == *kind) {
+            Some(shown) => {
+                unshown.swap_remove(shown);
+            }
+            None => commands.despawn(food_entity),
+        }
+    }
+    for food in unshown {
+        spawn_food(commands, config, materials, food);
+    }
+}
+
+/// Spawns one of a [Level]'s obstacles, tagged as a [Wall] so it's cleared along with the outer walls
+fn spawn_obstacle(
+    commands: &mut Commands,
+    config: &GameConfig,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    pos: GridPosition,
+) {
+    commands
+        .spawn(SpriteComponents {
+            material: materials.add(Color::BLACK.into()),
+            translation: grid_translation(config, pos),
+            sprite: Sprite {
+                size: Vec2::new(config.grid_unit, config.grid_unit),
                 ..Default::default()
-            })
-            .with(SnakeHead)
-            .with(head_pos)
-            .current_entity()
-            .unwrap(),
-    );
+            },
+            ..Default::default()
+        })
+        .with(Wall);
+}
+
+/// Spawns a bar just ahead of the head, lying along the snake's starting direction
+fn spawn_start_marker(
+    commands: &mut Commands,
+    config: &GameConfig,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    snake: &SimSnake,
+) {
+    let head = snake.head();
+    let ahead = head.step(snake.direction);
+    let unit = config.grid_unit;
+    let offset = Vec3::new((ahead.x - head.x) as f32, (ahead.y - head.y) as f32, 0.0) * unit * 0.75;
+    let size = if ahead.x != head.x {
+        Vec2::new(unit / 2.0, unit / 5.0)
+    } else {
+        Vec2::new(unit / 5.0, unit / 2.0)
+    };
 
     commands
-        .spawn((Snake {
-            body: snake_entity_list,
-            direction: SnakeDirection::Up,
-            last_direction: SnakeDirection::Up,
-        },))
-        .with(KeyBinds {
-            up: KeyCode::Up,
-            down: KeyCode::Down,
-            left: KeyCode::Left,
-            right: KeyCode::Right,
-        });
+        .spawn(SpriteComponents {
+            material: materials.add(Color::rgb(0.6, 0.6, 0.6).into()),
+            translation: Translation(grid_translation(config, head).0 + offset),
+            sprite: Sprite {
+                size,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .with(StartMarker);
+}
 
+/// Spawns a sprite at `pos` for one of `snake`'s segments.
+///
+/// Which segment is the head or tail isn't tagged on the entity, it follows from the order of [Snake::body].
+fn spawn_segment(
+    commands: &mut Commands,
+    config: &GameConfig,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    snake: Entity,
+    color: Color,
+    pos: GridPosition,
+) -> Entity {
     commands
         .spawn(SpriteComponents {
-            material: materials.add(Color::WHITE.into()),
-            translation: Translation(Vec3::new(constants::GRID_UNIT * -3.0, constants::GRID_UNIT * 2.0, 0.0)),
+            material: materials.add(color.into()),
+            translation: grid_translation(config, pos),
             sprite: Sprite {
-                size: Vec2::new(constants::GRID_UNIT / 2.0, constants::GRID_UNIT / 2.0),
+                size: Vec2::new(config.grid_unit, config.grid_unit),
                 ..Default::default()
             },
             ..Default::default()
         })
-        .with(Food)
-        .with(food_pos);
+        .with(pos)
+        .with(SegmentOf(snake))
+        .current_entity()
+        .unwrap()
+}
 
-    init_free_locations(&mut free_locations);
-    free_locations.0.remove(&tail_pos);
-    free_locations.0.remove(&body_pos);
-    free_locations.0.remove(&head_pos);
-    free_locations.0.remove(&food_pos);
+fn grid_translation(config: &GameConfig, pos: GridPosition) -> Translation {
+    Translation(Vec3::new(
+        config.grid_unit * pos.x as f32,
+        config.grid_unit * pos.y as f32,
+        0.0,
+    ))
 }
 
-fn despawn_game_entities(
+fn despawn_snake(
     commands: &mut Commands,
-    snake_entity_list: &LinkedList<Entity>,
-    food_entity: Entity,
+    snake_entity_list: &VecDeque<Entity>,
     snake_entity: Entity,
 ) {
     snake_entity_list.iter().for_each(|e| {
         commands.despawn(*e);
     });
-    commands.despawn(food_entity);
     commands.despawn(snake_entity);
 }
 
-fn init_free_locations(free_locations: &mut ResMut<FreeLocations>) {
-    free_locations.0.clear();
-    for x in -constants::GRID_SIZE..constants::GRID_SIZE {
-        for y in -constants::GRID_SIZE..constants::GRID_SIZE {
-            free_locations.0.insert(GridPosition::new(x, y));
-        }
+/// Clears the board: snakes, food and walls
+fn despawn_game_entities(
+    commands: &mut Commands,
+    snake_query: &mut Query<(&Snake, Entity)>,
+    food_query: &mut Query<(&Food, Entity)>,
+    wall_query: &mut Query<(&Wall, Entity)>,
+) {
+    // there may be no food left if the board was filled
+    for (_food, food_entity) in &mut food_query.iter() {
+        commands.despawn(food_entity);
+    }
+    for (snake, snake_entity) in &mut snake_query.iter() {
+        despawn_snake(commands, &snake.body, snake_entity);
+    }
+    for (_wall, wall_entity) in &mut wall_query.iter() {
+        commands.despawn(wall_entity);
     }
 }
 
+/// Advances the [SnakeWorld] whenever a snake's next step comes due and mirrors the outcome onto the segment entities.
+///
+/// Each snake steps on its own schedule, set by the [SpeedCurve](sim::SpeedCurve). Steps are taken in
+/// [RunClock](plugins::game_state::res::RunClock) order, and snakes due at the same moment move together,
+/// so the frame rate never changes how a run plays out.
 fn snake_movement_system(
     mut commands: Commands,
-    time: Res<Time>,
-    mut running_end_events: ResMut<Events<plugins::game_state::events::RunningGameEndEvent>>,
-    mut snake_timer: ResMut<SnakeMovementTimer>,
-    mut free_locations: ResMut<FreeLocations>,
+    config: Res<GameConfig>,
+    mut food_eaten_events: ResMut<Events<events::FoodEatenEvent>>,
+    machine: Res<plugins::game_state::res::GamePhaseMachine>,
+    run_clock: Res<plugins::game_state::res::RunClock>,
+    mut world: ResMut<SnakeWorld>,
+    mut rng: ResMut<GameRng>,
+    mut replay_mode: ResMut<ReplayMode>,
     mut materials: ResMut<Assets<ColorMaterial>>,
-    mut food_query: Query<(&Food, &mut GridPosition, &mut Translation)>,
-    mut snake_query: Query<(&mut Snake, &comp::Acting)>,
-    mut head_query: Query<(&SnakeHead, Entity, &GridPosition, &Translation)>,
-    mut tail_query: Query<(&SnakeTail, Entity, &mut GridPosition, &mut Translation)>,
-    mut body_query: Query<(&SnakeBody, Entity, &GridPosition, &Translation)>,
+    mut food_query: Query<(&Food, Entity, &FoodKind, &GridPosition)>,
+    mut snake_query: Query<(&mut Snake, &mut Score, Entity, &comp::Acting)>,
+    segment_query: Query<&mut GridPosition>,
 ) {
-    for (mut snake, _) in &mut snake_query.iter() {
-        snake_timer.0.tick(time.delta_seconds);
-        if !snake_timer.0.finished {
-            return;
+    if !machine.is(plugins::game_state::res::GamePhase::Running) {
+        return;
+    }
+
+    // steps from the time limit on never happen, however late in the frame it passes
+    let time_limit = config.time_limit().unwrap_or(std::f32::INFINITY);
+    loop {
+        if let ReplayMode::Playing(replay) = &*replay_mode {
+            play_back_forfeits(replay, &run_clock, &mut world, &mut snake_query);
+        }
+        if world.is_over() {
+            break;
+        }
+
+        let mut due: Option<f32> = None;
+        for (snake, _, _, _) in &mut snake_query.iter() {
+            let due_now = snake.next_step <= run_clock.elapsed && snake.next_step < time_limit;
+            if world.snakes[snake.player].alive && due_now {
+                due = Some(due.map_or(snake.next_step, |due| due.min(snake.next_step)));
+            }
+        }
+        let now = match due {
+            Some(now) => now,
+            None => break,
+        };
+
+        step_due_snakes(
+            &mut commands,
+            &config,
+            &mut food_eaten_events,
+            &run_clock,
+            &mut world,
+            &mut rng,
+            &mut replay_mode,
+            &mut materials,
+            &mut snake_query,
+            &segment_query,
+            now,
+        );
+    }
+
+    sync_food_sprites(&mut commands, &config, &mut materials, &mut food_query, &world.food);
+}
+
+/// Takes out the snakes whose players gave up at this point of the recorded run
+fn play_back_forfeits(
+    replay: &Replay,
+    run_clock: &plugins::game_state::res::RunClock,
+    world: &mut SnakeWorld,
+    snake_query: &mut Query<(&mut Snake, &mut Score, Entity, &comp::Acting)>,
+) {
+    for (mut snake, score, _, _) in &mut snake_query.iter() {
+        if snake.out.is_none() && replay.forfeited_at(world.tick, snake.player) {
+            let reason = plugins::game_state::events::GameOverReason::Forfeited;
+            snake.out = Some((reason, run_stats(&snake, &score, run_clock)));
+            world.eliminate(snake.player);
+        }
+    }
+}
+
+/// Takes one [SnakeWorld::step] moving every snake due at `now`, holding the rest where they are
+#[allow(clippy::too_many_arguments)]
+fn step_due_snakes(
+    commands: &mut Commands,
+    config: &GameConfig,
+    food_eaten_events: &mut Events<events::FoodEatenEvent>,
+    run_clock: &plugins::game_state::res::RunClock,
+    world: &mut SnakeWorld,
+    rng: &mut GameRng,
+    replay_mode: &mut ReplayMode,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    snake_query: &mut Query<(&mut Snake, &mut Score, Entity, &comp::Acting)>,
+    segment_query: &Query<&mut GridPosition>,
+    now: f32,
+) {
+    let mut inputs: Vec<Option<SnakeDirection>> = vec![None; world.snakes.len()];
+    for (mut snake, _, _, _) in &mut snake_query.iter() {
+        let player = snake.player;
+        if !world.snakes[player].alive || snake.next_step > now {
+            continue;
         }
-        snake_timer.0.reset();
-        for (_segment, head_entity, head_grid_pos, head_translation) in &mut head_query.iter() {
-            for (_segment, tail_entity, mut grid_pos, mut translation) in &mut tail_query.iter() {
-                let mut pending_next_pos = GridPosition::new(head_grid_pos.x, head_grid_pos.y);
-                let mut pending_translation = Translation(Vec3::new(
-                    head_translation.0.x(),
-                    head_translation.0.y(),
-                    head_translation.0.z(),
-                ));
-
-                match snake.direction {
-                    SnakeDirection::Up => {
-                        pending_next_pos.y += 1;
-                        *pending_translation.0.y_mut() += constants::GRID_UNIT
-                    }
-                    SnakeDirection::Down => {
-                        pending_next_pos.y = pending_next_pos.y - 1;
-                        *pending_translation.0.y_mut() += -constants::GRID_UNIT
-                    }
-                    SnakeDirection::Left => {
-                        pending_next_pos.x = pending_next_pos.x - 1;
-                        *pending_translation.0.x_mut() += -constants::GRID_UNIT
-                    }
-                    SnakeDirection::Right => {
-                        pending_next_pos.x += 1;
-                        *pending_translation.0.x_mut() += constants::GRID_UNIT
-                    }
+        let input = match replay_mode {
+            ReplayMode::Playing(replay) => replay.input_at(world.tick, player).unwrap_or(snake.direction),
+            ReplayMode::Recording { replay, .. } => {
+                let input = snake.next_direction();
+                if input != world.snakes[player].direction {
+                    replay.record(world.tick, player, input);
                 }
+                input
+            }
+            ReplayMode::Off => snake.next_direction(),
+        };
+        inputs[player] = Some(input);
+    }
 
-                snake.last_direction = snake.direction;
+    let outcomes = world.step(&inputs, now, rng);
 
-                for (_segment, _e, body_grid_pos, mut _translation) in &mut body_query.iter() {
-                    if *body_grid_pos == pending_next_pos {
-                        running_end_events.send(plugins::game_state::events::RunningGameEndEvent);
-                        return;
-                    }
+    for (mut snake, mut score, snake_entity, _) in &mut snake_query.iter() {
+        let player = snake.player;
+        snake.direction = world.snakes[player].direction;
+
+        let length = world.snakes[player].body.len();
+        match outcomes[player] {
+            StepOutcome::Moved { head, .. } => {
+                follow_sim_body(commands, config, materials, segment_query, snake_entity, &mut snake, head, length);
+            }
+            StepOutcome::Ate { head, kind } | StepOutcome::BoardFilled { head, kind } => {
+                follow_sim_body(commands, config, materials, segment_query, snake_entity, &mut snake, head, length);
+
+                score.0 += kind.points();
+                if let Some(factor) = kind.speed_factor() {
+                    snake.speed_effect = Some((factor, now + sim::SPEED_EFFECT_SECONDS));
                 }
+                food_eaten_events.send(events::FoodEatenEvent {
+                    snake: snake_entity,
+                    position: head,
+                    kind,
+                });
+            }
+            StepOutcome::HitSelf | StepOutcome::HitWall | StepOutcome::HitOtherSnake => {
+                let reason = match outcomes[player] {
+                    StepOutcome::HitSelf => plugins::game_state::events::GameOverReason::HitSelf,
+                    StepOutcome::HitOtherSnake => plugins::game_state::events::GameOverReason::HitOtherSnake,
+                    _ => plugins::game_state::events::GameOverReason::HitWall,
+                };
+                snake.out = Some((reason, run_stats(&snake, &score, run_clock)));
+            }
+            StepOutcome::Held | StepOutcome::Out => {}
+        }
 
-                for (_food, mut food_pos, mut food_translation) in &mut food_query.iter() {
-                    if pending_next_pos == *food_pos {
-                        commands.remove_one::<SnakeHead>(head_entity);
-                        commands.insert_one(head_entity, SnakeBody);
-
-                        let head_pos = food_pos.clone();
-                        let new_pos = get_random_location(&free_locations);
-
-                        food_pos.x = new_pos.x;
-                        food_pos.y = new_pos.y;
-
-                        *food_translation.0.x_mut() = constants::GRID_UNIT * food_pos.x as f32;
-                        *food_translation.0.y_mut() = constants::GRID_UNIT * food_pos.y as f32;
-
-                        println!("{:?}, {:?}", food_pos, food_translation.0);
-
-                        free_locations.0.remove(&*food_pos);
-
-                        snake.body.push_front(
-                            commands
-                                .spawn(SpriteComponents {
-                                    material: materials.add(Color::WHITE.into()),
-                                    translation: Translation(Vec3::new(
-                                        constants::GRID_UNIT * head_pos.x as f32,
-                                        constants::GRID_UNIT * head_pos.y as f32,
-                                        0.0,
-                                    )),
-                                    sprite: Sprite {
-                                        size: Vec2::new(constants::GRID_UNIT, constants::GRID_UNIT),
-                                        ..Default::default()
-                                    },
-                                    ..Default::default()
-                                })
-                                .with(SnakeHead)
-                                .with(head_pos)
-                                .current_entity()
-                                .unwrap(),
-                        );
-                    } else {
-                        free_locations
-                            .0
-                            .insert(GridPosition::new(grid_pos.x, grid_pos.y));
-
-                        grid_pos.x = pending_next_pos.x;
-                        grid_pos.y = pending_next_pos.y;
-
-                        *translation.0.x_mut() = pending_translation.0.x();
-                        *translation.0.y_mut() = pending_translation.0.y();
-
-                        free_locations.0.remove(&*grid_pos);
-
-                        commands.remove_one::<SnakeTail>(tail_entity);
-                        commands.insert_one(tail_entity, SnakeHead);
-
-                        commands.remove_one::<SnakeHead>(head_entity);
-                        commands.insert_one(head_entity, SnakeBody);
-
-                        if let Some(e) = snake.body.pop_back() {
-                            snake.body.push_front(e);
-                        }
-
-                        let tail_entity = snake.body.back().unwrap();
-
-                        for (_segment, entity, mut _grid_pos, mut _translation) in
-                            &mut body_query.iter()
-                        {
-                            if entity != *tail_entity {
-                                continue;
-                            }
-
-                            commands.remove_one::<SnakeBody>(entity);
-                            commands.insert_one(entity, SnakeTail);
-                        }
-                    }
+        if inputs[player].is_some() {
+            let grown = snake.body.len().saturating_sub(sim::START_LENGTH);
+            let eaten = world.snakes[player].eaten;
+            let mut step_seconds = config.speed.step_seconds(config.tick_seconds, eaten, grown, now);
+            if let Some((factor, until)) = snake.speed_effect {
+                if now < until {
+                    step_seconds *= factor;
+                } else {
+                    snake.speed_effect = None;
                 }
             }
+            snake.step_seconds = step_seconds.max(sim::MIN_STEP_SECONDS);
+            snake.next_step = now + snake.step_seconds;
         }
     }
 }
 
-fn player_input_system(
-    keyboard_input: Res<Input<KeyCode>>,
-    mut snake_query: Query<(&mut Snake, &KeyBinds)>,
+/// Lines `snake`'s segment entities up with its body in the [SnakeWorld] after its head moved to `head`,
+/// leaving it `length` segments long
+#[allow(clippy::too_many_arguments)]
+fn follow_sim_body(
+    commands: &mut Commands,
+    config: &GameConfig,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    segment_query: &Query<&mut GridPosition>,
+    snake_entity: Entity,
+    snake: &mut Snake,
+    head: GridPosition,
+    length: usize,
 ) {
-    for (mut snake, keybinds) in &mut snake_query.iter() {
-        if keyboard_input.just_pressed(keybinds.up) {
-            if let SnakeDirection::Down = snake.last_direction {
-                return;
-            }
-            snake.direction = SnakeDirection::Up;
+    if snake.body.len() < length {
+        grow_snake(commands, config, materials, snake_entity, snake, head);
+    } else {
+        // the tail segment jumps to the front and becomes the head
+        let tail = snake.body.pop_back().unwrap();
+        if let Ok(mut pos) = segment_query.get_mut::<GridPosition>(tail) {
+            *pos = head;
         }
-        if keyboard_input.just_pressed(keybinds.down) {
-            if let SnakeDirection::Up = snake.last_direction {
-                return;
-            }
-            snake.direction = SnakeDirection::Down;
+        snake.body.push_front(tail);
+    }
+    // shrinking leaves segments behind the new tail
+    while snake.body.len() > length {
+        commands.despawn(snake.body.pop_back().unwrap());
+    }
+}
+
+/// Spawns a new head segment at `head`, leaving the tail where it is
+fn grow_snake(
+    commands: &mut Commands,
+    config: &GameConfig,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    snake_entity: Entity,
+    snake: &mut Snake,
+    head: GridPosition,
+) {
+    let segment = spawn_segment(commands, config, materials, snake_entity, PLAYER_COLORS[snake.player], head);
+    snake.body.push_front(segment);
+}
+
+fn run_stats(
+    snake: &Snake,
+    score: &Score,
+    run_clock: &plugins::game_state::res::RunClock,
+) -> plugins::game_state::events::RunStats {
+    plugins::game_state::events::RunStats {
+        score: score.0,
+        length: snake.body.len(),
+        duration: run_clock.elapsed,
+    }
+}
+
+/// Set once [run_end_system] has reported the end of the current run, until the run is left
+#[derive(Default)]
+struct RunEndSent(bool);
+
+/// Ends the run once the [SnakeWorld] says it's over, counting any snake still going as a winner.
+/// The end is only reported once, however many frames pass before the phase moves on, and not at all
+/// if a snake completed the level, which the campaign reports instead.
+///
+/// Until then, snakes that are out are cleared off the board so the others can play on.
+fn run_end_system(
+    mut commands: Commands,
+    mut machine: ResMut<plugins::game_state::res::GamePhaseMachine>,
+    run_clock: Res<plugins::game_state::res::RunClock>,
+    world: Res<SnakeWorld>,
+    mut sent: ResMut<RunEndSent>,
+    mut running_end_events: ResMut<Events<plugins::game_state::events::RunningGameEndEvent>>,
+    mut snake_query: Query<(&mut Snake, &Score, Entity, &comp::Acting)>,
+    mut segment_query: Query<(&SegmentOf, Entity)>,
+) {
+    use plugins::game_state::events::{GameOverReason, RunResult, RunningGameEndEvent};
+
+    if !machine.is(plugins::game_state::res::GamePhase::Running) {
+        // pausing suspends the run rather than leaving it
+        if !machine.is(plugins::game_state::res::GamePhase::Paused) {
+            sent.0 = false;
+        }
+        return;
+    }
+    if world.completed.is_some() {
+        return;
+    }
+    if sent.0 {
+        // the end was reported but the run carried on, so a pause must have swallowed the move to PostGame
+        if machine.pending().is_none() {
+            machine.request(plugins::game_state::res::GamePhase::PostGame);
         }
-        if keyboard_input.just_pressed(keybinds.left) {
-            if let SnakeDirection::Right = snake.last_direction {
-                return;
+        return;
+    }
+
+    if !world.is_over() {
+        let mut cleared = Vec::new();
+        for (mut snake, _, snake_entity, _) in &mut snake_query.iter() {
+            if snake.out.is_some() && !snake.body.is_empty() {
+                snake.body.clear();
+                cleared.push(snake_entity);
             }
-            snake.direction = SnakeDirection::Left;
         }
-        if keyboard_input.just_pressed(keybinds.right) {
-            if let SnakeDirection::Left = snake.last_direction {
-                return;
+        if !cleared.is_empty() {
+            for (segment_of, segment) in &mut segment_query.iter() {
+                if cleared.contains(&segment_of.0) {
+                    commands.despawn(segment);
+                }
             }
-            snake.direction = SnakeDirection::Right;
         }
+        return;
     }
+
+    let survivor_reason = if world.food.is_empty() {
+        GameOverReason::BoardFilled
+    } else {
+        GameOverReason::LastStanding
+    };
+    let mut results = Vec::new();
+    for (mut snake, score, snake_entity, _) in &mut snake_query.iter() {
+        if snake.out.is_none() {
+            snake.out = Some((survivor_reason, run_stats(&snake, &score, &run_clock)));
+        }
+        let (reason, stats) = snake.out.unwrap();
+        results.push(RunResult {
+            snake: snake_entity,
+            player: snake.player,
+            reason,
+            stats,
+        });
+    }
+    results.sort_by_key(|result| result.player);
+    running_end_events.send(RunningGameEndEvent { results });
+    sent.0 = true;
 }
 
-fn snake_collision_system(
-    mut run_end_events: ResMut<Events<plugins::game_state::events::RunningGameEndEvent>>,
-    mut snake_query: Query<(&Snake, &comp::Acting)>,
-    mut head_query: Query<(&SnakeHead, &GridPosition)>,
+/// Ends the run once it has lasted longer than [GameConfig::time_limit()].
+///
+/// The run ends only once every step scheduled before the limit has been taken, so it always ends
+/// after the same step whichever order the systems run in.
+fn time_limit_system(
+    config: Res<GameConfig>,
+    machine: Res<plugins::game_state::res::GamePhaseMachine>,
+    run_clock: Res<plugins::game_state::res::RunClock>,
+    mut world: ResMut<SnakeWorld>,
+    mut snake_query: Query<(&mut Snake, &Score, &comp::Acting)>,
+) {
+    let time_limit = match config.time_limit() {
+        Some(time_limit) => time_limit,
+        None => return,
+    };
+    if !machine.is(plugins::game_state::res::GamePhase::Running) || run_clock.elapsed < time_limit {
+        return;
+    }
+    for (snake, _, _) in &mut snake_query.iter() {
+        if world.snakes[snake.player].alive && snake.next_step < time_limit {
+            return;
+        }
+    }
+
+    for (mut snake, score, _) in &mut snake_query.iter() {
+        if snake.out.is_none() {
+            let reason = plugins::game_state::events::GameOverReason::TimedOut;
+            let mut stats = run_stats(&snake, &score, &run_clock);
+            stats.duration = time_limit;
+            snake.out = Some((reason, stats));
+            world.eliminate(snake.player);
+        }
+    }
+}
+
+/// Takes a player's snake out of the run when they press their forfeit key or button.
+///
+/// Playback takes the forfeits from the replay instead, see [play_back_forfeits].
+fn forfeit_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepad_input: Res<plugins::gamepad::res::GamepadInput>,
+    machine: Res<plugins::game_state::res::GamePhaseMachine>,
+    run_clock: Res<plugins::game_state::res::RunClock>,
+    mut world: ResMut<SnakeWorld>,
+    mut replay_mode: ResMut<ReplayMode>,
+    mut snake_query: Query<(&mut Snake, &Score, &InputBinding, &comp::Acting)>,
 ) {
-    for (_, _) in &mut snake_query.iter() {
-        for (_head, pos) in &mut head_query.iter() {
-            if pos.x > constants::GRID_SIZE || pos.x < -constants::GRID_SIZE || pos.y > constants::GRID_SIZE || pos.y < -constants::GRID_SIZE {
-                run_end_events.send(plugins::game_state::events::RunningGameEndEvent);
+    if !machine.is(plugins::game_state::res::GamePhase::Running) {
+        return;
+    }
+    if let ReplayMode::Playing(_) = *replay_mode {
+        return;
+    }
+
+    for (mut snake, score, binding, _) in &mut snake_query.iter() {
+        if snake.out.is_none() && binding.forfeit_pressed(&keyboard_input, &gamepad_input) {
+            let reason = plugins::game_state::events::GameOverReason::Forfeited;
+            snake.out = Some((reason, run_stats(&snake, &score, &run_clock)));
+            world.eliminate(snake.player);
+            if let ReplayMode::Recording { replay, .. } = &mut *replay_mode {
+                replay.record_forfeit(world.tick, snake.player);
             }
         }
     }
 }
 
+/// Keeps every sprite's [Translation] in line with its [GridPosition], which is the source of truth.
+fn grid_translation_system(
+    config: Res<GameConfig>,
+    mut query: Query<(&GridPosition, &mut Translation)>,
+) {
+    for (pos, mut translation) in &mut query.iter() {
+        *translation.0.x_mut() = config.grid_unit * pos.x as f32;
+        *translation.0.y_mut() = config.grid_unit * pos.y as f32;
+    }
+}
+
+fn player_input_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepad_input: Res<plugins::gamepad::res::GamepadInput>,
+    replay_mode: Res<ReplayMode>,
+    machine: Res<plugins::game_state::res::GamePhaseMachine>,
+    mut snake_query: Query<(&mut Snake, &InputBinding)>,
+) {
+    if let ReplayMode::Playing(_) = *replay_mode {
+        return;
+    }
+    // turns pressed while paused are dropped rather than applied on resume
+    if machine.is(plugins::game_state::res::GamePhase::Paused) {
+        return;
+    }
+
+    for (mut snake, binding) in &mut snake_query.iter() {
+        for direction in binding.turns(&keyboard_input, &gamepad_input) {
+            snake.queue_turn(direction);
+        }
+    }
+}
+
 #[derive(Default)]
 struct RunningGameEndListenerState {
-    event_reader: EventReader<plugins::game_state::events::RunningGameEndEvent>
+    event_reader: EventReader<plugins::game_state::events::RunningGameEndEvent>,
+    level_complete_reader: EventReader<plugins::game_state::events::LevelCompleteEvent>,
 }
 
+/// Stops the snakes and saves the replay once a run ends, whether it was lost or the level was completed
 fn process_running_end_events(
     mut commands: Commands,
     mut state: ResMut<RunningGameEndListenerState>,
     running_end_events: Res<Events<plugins::game_state::events::RunningGameEndEvent>>,
+    level_complete_events: Res<Events<plugins::game_state::events::LevelCompleteEvent>>,
+    replay_mode: Res<ReplayMode>,
     mut snake_query: Query<(&Snake, Entity)>,
 ) {
-    for _ in state.event_reader.iter(&running_end_events) {
+    let ended = state.event_reader.iter(&running_end_events).count()
+        + state.level_complete_reader.iter(&level_complete_events).count();
+    if ended > 0 {
         for (_, e) in &mut snake_query.iter() {
             commands.remove_one::<comp::Acting>(e);
         }
-    }
-}
 
-#[derive(Default)]
-struct PostGameEndListenerState {
-    event_reader: EventReader<plugins::game_state::events::PostGameEndEvent>
-}
-
-fn process_post_end_events(
-    mut commands: Commands,
-    mut state: ResMut<PostGameEndListenerState>,
-    post_end_events: Res<Events<plugins::game_state::events::PostGameEndEvent>>,
-    mut snake_query: Query<(&Snake, Entity)>,
-    mut food_query: Query<(&Food, Entity)>,
-) {
-    for _ in state.event_reader.iter(&post_end_events) {
-        for (_food, food_entity) in &mut food_query.iter() {
-            for (snake, snake_entity) in &mut snake_query.iter() {
-                despawn_game_entities(&mut commands, &snake.body, food_entity, snake_entity);
+        if let ReplayMode::Recording { path, replay } = &*replay_mode {
+            if let Err(e) = replay.save(path) {
+                eprintln!("Could not save replay {}: {}", path.display(), e);
             }
         }
     }
 }
 
 #[derive(Default)]
-struct PreGameStartListenerState {
-    event_reader: EventReader<plugins::game_state::events::PreGameStartEvent>
+struct PhaseChangedListenerState {
+    event_reader: EventReader<plugins::game_state::res::GamePhaseChanged>
 }
 
-fn process_pre_start_events(
+/// Sets up, starts and tears down the game entities as the [GamePhaseMachine](plugins::game_state::res::GamePhaseMachine) moves along
+fn process_phase_changes(
     mut commands: Commands,
-    mut state: ResMut<PreGameStartListenerState>,
-    pre_start_events: Res<Events<plugins::game_state::events::PreGameStartEvent>>,
+    mut state: ResMut<PhaseChangedListenerState>,
+    phase_events: Res<Events<plugins::game_state::res::GamePhaseChanged>>,
+    config: Res<GameConfig>,
+    settings: Res<settings::Settings>,
+    levels: Res<Levels>,
     mut materials: ResMut<Assets<ColorMaterial>>,
-    mut free_locations: ResMut<FreeLocations>
+    mut world: ResMut<SnakeWorld>,
+    mut rng: ResMut<GameRng>,
+    mut replay_mode: ResMut<ReplayMode>,
+    mut snake_query: Query<(&Snake, Entity)>,
+    mut food_query: Query<(&Food, Entity)>,
+    mut wall_query: Query<(&Wall, Entity)>,
+    mut marker_query: Query<(&StartMarker, Entity)>,
 ) {
-    for _ in state.event_reader.iter(&pre_start_events) {
-        spawn_game_entities(&mut commands, &mut materials, &mut free_locations);
-    }
-}
+    use plugins::game_state::res::GamePhase;
 
-#[derive(Default)]
-struct PreGameEndListenerState {
-    event_reader: EventReader<plugins::game_state::events::PreGameEndEvent>
-}
+    for event in state.event_reader.iter(&phase_events) {
+        if event.kind == plugins::game_state::events::PhaseChangeKind::Resume {
+            continue;
+        }
 
-fn process_pre_end_events(
-    mut state: ResMut<PreGameEndListenerState>,
-    pre_end_events: Res<Events<plugins::game_state::events::PreGameEndEvent>>,
-    mut snake_timer: ResMut<SnakeMovementTimer>,
-) {
-    for _ in state.event_reader.iter(&pre_end_events) {
-        snake_timer.0.reset();
+        match event.to {
+            GamePhase::Menu => {
+                despawn_game_entities(&mut commands, &mut snake_query, &mut food_query, &mut wall_query);
+            }
+            GamePhase::PreGame => {
+                // the board is rebuilt every game, the menu may have changed its size
+                despawn_game_entities(&mut commands, &mut snake_query, &mut food_query, &mut wall_query);
+
+                spawn_game_entities(&mut commands, &config, &settings, &levels, &mut materials, &mut world, &mut rng);
+
+                if let ReplayMode::Recording { replay, .. } = &mut *replay_mode {
+                    *replay = Replay::new(rng.seed(), &config, world.snakes.len());
+                }
+            }
+            GamePhase::Running => {
+                for (_, e) in &mut marker_query.iter() {
+                    commands.despawn(e);
+                }
+                for (_, e) in &mut snake_query.iter() {
+                    commands.insert_one(e, comp::Acting);
+                }
+            }
+            GamePhase::PostGame | GamePhase::LevelComplete | GamePhase::Paused => {}
+        }
     }
 }
 
@@ -491,8 +858,3 @@ fn debug_food_sprite_system(time: Res<Time>, mut query: Query<(&Food, &mut Trans
         *translation.0.x_mut() += 30.0 * time.delta_seconds;
     }
 }
-
-fn get_random_location(locations: &ResMut<FreeLocations>) -> GridPosition {
-    let index = rand::thread_rng().gen_range(0, locations.0.len());
-    *locations.0.iter().nth(index).unwrap()
-}
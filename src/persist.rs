@@ -0,0 +1,42 @@
+//! The RON files kept between sessions: high scores, settings and campaign progress
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// `<dir>/bevy_snake/<file_name>`, `None` if the platform has no such directory
+pub fn user_file(dir: Option<PathBuf>, file_name: &str) -> Option<PathBuf> {
+    dir.map(|dir| dir.join("bevy_snake").join(file_name))
+}
+
+pub fn load<T: DeserializeOwned>(path: &Path) -> Result<T, Box<dyn Error>> {
+    let contents = fs::read_to_string(path)?;
+    Ok(ron::de::from_str(&contents)?)
+}
+
+/// Reads `path`, falling back to the default if there's no path or no file yet,
+/// or if the file is invalid, which is reported as invalid `what`
+pub fn load_or_default<T: DeserializeOwned + Default>(path: Option<&Path>, what: &str) -> T {
+    match path {
+        Some(path) if path.exists() => load(path).unwrap_or_else(|e| {
+            eprintln!("Ignoring invalid {} {}: {}", what, path.display(), e);
+            T::default()
+        }),
+        _ => T::default(),
+    }
+}
+
+/// Writes `value` to `path`, creating its directory if needed. Does nothing without a path.
+pub fn save<T: Serialize>(path: Option<&Path>, value: &T) -> Result<(), Box<dyn Error>> {
+    let path = match path {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let contents = ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())?;
+    fs::write(path, contents)?;
+    Ok(())
+}
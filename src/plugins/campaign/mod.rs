@@ -0,0 +1,20 @@
+use bevy::prelude::*;
+
+pub mod res;
+pub mod sys;
+
+/// Tag for the text shown on the level complete screen
+pub struct LevelCompleteText;
+
+pub struct CampaignPlugin;
+impl Plugin for CampaignPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app
+        .add_resource(res::CampaignProgress::load_or_default())
+        .add_resource(res::LevelCompleteListenerState::default())
+        .add_resource(res::PhaseChangedListenerState::default())
+        .add_system(sys::level_goal_system.system())
+        .add_system(sys::level_complete_screen_system.system())
+        .add_system(sys::level_complete_choice_system.system());
+    }
+}
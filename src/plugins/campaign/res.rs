@@ -0,0 +1,72 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::PathBuf;
+
+use crate::persist;
+use crate::plugins::game_state::{events, res::GamePhaseChanged};
+use crate::sim::{Level, Levels};
+
+/// How far the player has got through the campaign, kept between sessions
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CampaignProgress {
+    /// Index into [Levels::campaign] of the level to play next
+    pub next: usize,
+    /// Set once every level of the campaign has been completed
+    pub finished: bool,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl CampaignProgress {
+    /// `<user data dir>/bevy_snake/campaign.ron`
+    pub fn default_path() -> Option<PathBuf> {
+        persist::user_file(dirs::data_dir(), "campaign.ron")
+    }
+
+    /// Reads the progress from [CampaignProgress::default_path], starting from the first level if it doesn't exist yet
+    pub fn load_or_default() -> Self {
+        let path = CampaignProgress::default_path();
+        let mut progress: CampaignProgress = persist::load_or_default(path.as_deref(), "campaign progress");
+        progress.path = path;
+        progress
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        persist::save(self.path.as_deref(), self)
+    }
+
+    /// The level to play next, `None` if there are no campaign levels at all
+    pub fn current<'a>(&self, levels: &'a Levels) -> Option<&'a Level> {
+        let campaign = levels.campaign();
+        campaign.get(self.next).or_else(|| campaign.first()).copied()
+    }
+
+    /// Moves on from the level called `name`, back to the first level after the last.
+    ///
+    /// Replaying a level completed earlier doesn't move progress backwards.
+    pub fn complete(&mut self, levels: &Levels, name: &str) {
+        let campaign = levels.campaign();
+        let index = match campaign.iter().position(|level| level.name == name) {
+            Some(index) => index,
+            None => return,
+        };
+        if index + 1 == campaign.len() {
+            self.finished = true;
+            self.next = 0;
+        } else if index >= self.next {
+            self.next = index + 1;
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct LevelCompleteListenerState {
+    pub event_reader: EventReader<events::LevelCompleteEvent>
+}
+
+#[derive(Default)]
+pub struct PhaseChangedListenerState {
+    pub event_reader: EventReader<GamePhaseChanged>
+}
@@ -0,0 +1,126 @@
+use bevy::prelude::*;
+
+use crate::comp::{self, snake::{Score, Snake}};
+use crate::config::GameConfig;
+use crate::constants;
+use crate::plugins::campaign::{res, LevelCompleteText};
+use crate::plugins::game_state::events::{LevelCompleteEvent, RunStats};
+use crate::plugins::game_state::res::{GamePhase, GamePhaseChanged, GamePhaseMachine};
+use crate::sim::{Levels, SnakeWorld};
+use crate::ui;
+
+/// Completes the level once a step of the [SnakeWorld] has seen a snake meet its goal.
+///
+/// The world decides that at step time, ahead of any other end to the run, so no
+/// [RunningGameEndEvent](crate::plugins::game_state::events::RunningGameEndEvent) is sent for the same run.
+/// Progress is saved straight away and the config moves on to the next level, ready for the next
+/// [GamePhase::PreGame].
+pub fn level_goal_system(
+    mut config: ResMut<GameConfig>,
+    levels: Res<Levels>,
+    world: Res<SnakeWorld>,
+    mut progress: ResMut<res::CampaignProgress>,
+    mut machine: ResMut<GamePhaseMachine>,
+    mut level_complete_events: ResMut<Events<LevelCompleteEvent>>,
+    mut snake_query: Query<(&Snake, &Score, &comp::Acting)>,
+) {
+    if !machine.is(GamePhase::Running) {
+        return;
+    }
+    let (player, duration) = match world.completed {
+        Some(completed) => completed,
+        None => return,
+    };
+    let name = match config.level.clone() {
+        Some(name) => name,
+        None => return,
+    };
+
+    for (snake, score, _) in &mut snake_query.iter() {
+        if snake.player != player {
+            continue;
+        }
+        level_complete_events.send(LevelCompleteEvent {
+            level: name.clone(),
+            player,
+            stats: RunStats {
+                score: score.0,
+                length: snake.body.len(),
+                duration,
+            },
+        });
+    }
+    progress.complete(&levels, &name);
+    if let Err(e) = progress.save() {
+        eprintln!("Could not save campaign progress: {}", e);
+    }
+    config.level = progress.current(&levels).map(|level| level.name.clone());
+    machine.request(GamePhase::LevelComplete);
+}
+
+/// Shows which level was completed and what comes next while in [GamePhase::LevelComplete]
+pub fn level_complete_screen_system(
+    mut commands: Commands,
+    mut complete_state: ResMut<res::LevelCompleteListenerState>,
+    mut phase_state: ResMut<res::PhaseChangedListenerState>,
+    level_complete_events: Res<Events<LevelCompleteEvent>>,
+    phase_events: Res<Events<GamePhaseChanged>>,
+    asset_server: Res<AssetServer>,
+    config: Res<GameConfig>,
+    levels: Res<Levels>,
+    mut text_query: Query<(&LevelCompleteText, Entity)>,
+) {
+    for event in phase_state.event_reader.iter(&phase_events) {
+        if event.exited(GamePhase::LevelComplete) {
+            for (_, e) in &mut text_query.iter() {
+                commands.despawn(e);
+            }
+        }
+    }
+
+    for event in complete_state.event_reader.iter(&level_complete_events) {
+        let campaign = levels.campaign();
+        let finished = campaign.last().map_or(true, |level| level.name == event.level);
+        let next = config.level.as_ref().and_then(|name| levels.find(name));
+
+        let mut rows = vec![
+            (String::from("Level complete!"), ui::HIGHLIGHT_COLOR),
+            (
+                format!("{} in {:.1}s, {} long", event.level, event.stats.duration, event.stats.length),
+                Color::WHITE,
+            ),
+        ];
+        if finished {
+            rows.push((String::from("Campaign complete!"), ui::HIGHLIGHT_COLOR));
+        }
+        if let Some(next) = next {
+            let goal = next.goal.map(|goal| goal.to_string()).unwrap_or_default();
+            rows.push((format!("Next: {}  {}", next.name, goal), Color::WHITE));
+        }
+        let hint = if finished { "Enter: start over   Esc: menu" } else { "Enter: next level   Esc: menu" };
+        rows.push((String::from(hint), Color::WHITE));
+
+        let font = asset_server.load(constants::UI_FONT).unwrap();
+        for (i, (value, color)) in rows.into_iter().enumerate() {
+            commands
+                .spawn(ui::text(font, value, 28.0, color, ui::top_left(40.0, 100.0 + 36.0 * i as f32)))
+                .with(LevelCompleteText);
+        }
+    }
+}
+
+/// Moves on to the next level or back to the menu from [GamePhase::LevelComplete]
+pub fn level_complete_choice_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut machine: ResMut<GamePhaseMachine>,
+) {
+    if !machine.is(GamePhase::LevelComplete) {
+        return;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Return) {
+        machine.request(GamePhase::PreGame);
+    } else if keyboard_input.just_pressed(KeyCode::Escape) {
+        machine.request(GamePhase::Menu);
+    }
+}
@@ -1,19 +1,114 @@
-/// Event fired when the game state begins the [PreGamePhase]
-pub struct PreGameStartEvent;
+use bevy::prelude::*;
+use std::fmt;
 
-/// Event fired when the game state finishes the [PreGamePhase]
-pub struct PreGameEndEvent;
+/// Event fired whenever a [PhaseMachine](super::machine::PhaseMachine) enters a phase.
+///
+/// `from` is `None` when the machine announces its initial phase.
+#[derive(Debug, Copy, Clone)]
+pub struct PhaseChanged<P> {
+    pub from: Option<P>,
+    pub to: P,
+    pub kind: PhaseChangeKind,
+}
+impl<P: PartialEq> PhaseChanged<P> {
+    /// Whether `phase` was started afresh, rather than resumed
+    pub fn entered(&self, phase: P) -> bool {
+        self.to == phase && self.kind != PhaseChangeKind::Resume
+    }
+
+    /// Whether `phase` is over, rather than just suspended
+    pub fn exited(&self, phase: P) -> bool {
+        self.from == Some(phase) && self.kind != PhaseChangeKind::Suspend
+    }
+}
+
+/// How a [PhaseChanged] came about
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PhaseChangeKind {
+    /// The `to` phase starts from the beginning
+    Enter,
+    /// The `from` phase was set aside and will be resumed later
+    Suspend,
+    /// The `to` phase picks up where it was suspended
+    Resume,
+}
 
-/// Event fired when the game state starts the [RunningGamePhase]
-pub struct RunningGameStartEvent;
+/// Event fired once per whole second of the [GamePhase::PreGame](super::res::GamePhase::PreGame) countdown,
+/// and once more with `remaining: 0` when the run starts
+#[derive(Debug, Copy, Clone)]
+pub struct CountdownTickEvent {
+    /// Whole seconds left before the run starts
+    pub remaining: u32,
+}
 
-/// Event fired when the game state ends the [RunningGamePhase]
+/// Event fired by the game logic to end the [GamePhase::Running](super::res::GamePhase::Running) phase
 ///
 /// TODO: Maybe figure out a better way to fire this rather than directly from the game logic
-pub struct RunningGameEndEvent;
+pub struct RunningGameEndEvent {
+    /// How the run went for each snake, in player order
+    pub results: Vec<RunResult>,
+}
+
+/// Event fired by the campaign once a snake meets the level's goal, ending the
+/// [GamePhase::Running](super::res::GamePhase::Running) phase without anyone losing
+pub struct LevelCompleteEvent {
+    /// Name of the [Level](crate::sim::Level) completed
+    pub level: String,
+    /// The player whose snake met the goal
+    pub player: usize,
+    pub stats: RunStats,
+}
+
+/// How the run ended for one snake
+#[derive(Debug, Copy, Clone)]
+pub struct RunResult {
+    pub snake: Entity,
+    pub player: usize,
+    pub reason: GameOverReason,
+    pub stats: RunStats,
+}
 
-/// Event fired when the game state begins the [PostGamePhase]
-pub struct PostGameStartEvent;
+/// Why a snake's run ended
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GameOverReason {
+    HitWall,
+    HitSelf,
+    HitOtherSnake,
+    TimedOut,
+    /// The snake filled every free cell, this is a win
+    BoardFilled,
+    Forfeited,
+    /// Every other snake is out, this is a win
+    LastStanding,
+}
+impl GameOverReason {
+    pub fn is_victory(self) -> bool {
+        match self {
+            GameOverReason::BoardFilled | GameOverReason::LastStanding => true,
+            _ => false,
+        }
+    }
+}
+impl fmt::Display for GameOverReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = match self {
+            GameOverReason::HitWall => "Hit the wall",
+            GameOverReason::HitSelf => "Ran into itself",
+            GameOverReason::HitOtherSnake => "Ran into another snake",
+            GameOverReason::TimedOut => "Out of time",
+            GameOverReason::BoardFilled => "Filled the board!",
+            GameOverReason::Forfeited => "Forfeited",
+            GameOverReason::LastStanding => "Last snake standing!",
+        };
+        f.write_str(description)
+    }
+}
 
-/// Event fired when the game state finishes the [PostGamePhase]
-pub struct PostGameEndEvent;
\ No newline at end of file
+/// A snake's totals at the end of a run
+#[derive(Debug, Copy, Clone)]
+pub struct RunStats {
+    pub score: u32,
+    pub length: usize,
+    /// Seconds the [GamePhase::Running](super::res::GamePhase::Running) phase lasted
+    pub duration: f32,
+}
@@ -0,0 +1,216 @@
+use std::fmt::Debug;
+
+use super::events::{PhaseChangeKind, PhaseChanged};
+
+/// A state a [PhaseMachine] can be in
+pub trait Phase: Debug + Copy + Eq + Send + Sync + 'static {
+    /// How long the phase lasts before its [Trigger::Timeout] transition fires, `None` if it waits for a request.
+    ///
+    /// This is only the default, see [PhaseMachine::set_duration].
+    fn duration(&self) -> Option<f32>;
+}
+
+/// What makes a [Transition] fire
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Trigger {
+    /// The `from` phase ran for its full [Phase::duration]
+    Timeout,
+    /// Game logic called [PhaseMachine::request]
+    Request,
+    /// Game logic called [PhaseMachine::request], and the `from` phase is set aside until [PhaseMachine::resume]
+    Suspend,
+}
+
+/// One row of a [PhaseMachine]'s transition table
+#[derive(Debug, Copy, Clone)]
+pub struct Transition<P: Phase> {
+    pub from: P,
+    pub to: P,
+    pub trigger: Trigger,
+}
+
+impl<P: Phase> Transition<P> {
+    pub fn timeout(from: P, to: P) -> Self {
+        Transition {
+            from,
+            to,
+            trigger: Trigger::Timeout,
+        }
+    }
+
+    pub fn request(from: P, to: P) -> Self {
+        Transition {
+            from,
+            to,
+            trigger: Trigger::Request,
+        }
+    }
+
+    pub fn suspend(from: P, to: P) -> Self {
+        Transition {
+            from,
+            to,
+            trigger: Trigger::Suspend,
+        }
+    }
+}
+
+enum Pending<P> {
+    Enter(P),
+    Suspend(P),
+    Resume,
+}
+
+/// A table driven state machine over the phases `P`.
+///
+/// [phase_machine_system](super::sys::phase_machine_system) ticks it and fires a
+/// [PhaseChanged](super::events::PhaseChanged) event whenever it moves to a new phase.
+pub struct PhaseMachine<P: Phase> {
+    current: P,
+    /// Seconds spent in the current phase
+    elapsed: f32,
+    /// Set until the initial phase has been announced
+    entering: bool,
+    pending: Option<Pending<P>>,
+    /// The phase set aside by a [Trigger::Suspend] transition, and how far into it we were
+    suspended: Option<(P, f32)>,
+    transitions: Vec<Transition<P>>,
+    /// Durations set with [PhaseMachine::set_duration], taking precedence over [Phase::duration]
+    durations: Vec<(P, Option<f32>)>,
+}
+
+impl<P: Phase> PhaseMachine<P> {
+    pub fn new(initial: P, transitions: Vec<Transition<P>>) -> Self {
+        PhaseMachine {
+            current: initial,
+            elapsed: 0.0,
+            entering: true,
+            pending: None,
+            suspended: None,
+            transitions,
+            durations: Vec::new(),
+        }
+    }
+
+    /// Overrides how long `phase` lasts, e.g. from a config file
+    pub fn set_duration(&mut self, phase: P, duration: Option<f32>) {
+        self.durations.retain(|(p, _)| *p != phase);
+        self.durations.push((phase, duration));
+    }
+
+    /// How long `phase` lasts before it times out, if it does
+    pub fn duration(&self, phase: P) -> Option<f32> {
+        match self.durations.iter().find(|(p, _)| *p == phase) {
+            Some((_, duration)) => *duration,
+            None => phase.duration(),
+        }
+    }
+
+    pub fn current(&self) -> P {
+        self.current
+    }
+
+    pub fn is(&self, phase: P) -> bool {
+        self.current == phase
+    }
+
+    /// The phase a [PhaseMachine::request] is waiting to move to on the next tick, if any
+    pub fn pending(&self) -> Option<P> {
+        match self.pending {
+            Some(Pending::Enter(phase)) | Some(Pending::Suspend(phase)) => Some(phase),
+            _ => None,
+        }
+    }
+
+    /// Seconds left before the current phase times out, if it has a duration
+    pub fn remaining(&self) -> Option<f32> {
+        self.duration(self.current)
+            .map(|duration| (duration - self.elapsed).max(0.0))
+    }
+
+    /// Asks to move to `to` on the next tick.
+    ///
+    /// Returns `false` and does nothing if the table has no [Trigger::Request] or [Trigger::Suspend] row
+    /// from the current phase to `to`, or if `to` would suspend the current phase while a move to another
+    /// phase is already waiting, since that move would be lost.
+    pub fn request(&mut self, to: P) -> bool {
+        let current = self.current;
+        let trigger = self
+            .transitions
+            .iter()
+            .find(|t| t.from == current && t.to == to && t.trigger != Trigger::Timeout)
+            .map(|t| t.trigger);
+        match trigger {
+            Some(Trigger::Suspend) if matches!(self.pending, Some(Pending::Enter(_))) => return false,
+            Some(Trigger::Suspend) => self.pending = Some(Pending::Suspend(to)),
+            Some(_) => self.pending = Some(Pending::Enter(to)),
+            None => return false,
+        }
+        true
+    }
+
+    /// Asks to go back to the suspended phase on the next tick, picking up its timer where it left off.
+    ///
+    /// Returns `false` and does nothing if no phase is suspended.
+    pub fn resume(&mut self) -> bool {
+        if self.suspended.is_none() {
+            return false;
+        }
+        self.pending = Some(Pending::Resume);
+        true
+    }
+
+    /// Advances the current phase by `delta` seconds, returning the change if the machine entered a phase.
+    pub fn tick(&mut self, delta: f32) -> Option<PhaseChanged<P>> {
+        if self.entering {
+            self.entering = false;
+            return Some(PhaseChanged {
+                from: None,
+                to: self.current,
+                kind: PhaseChangeKind::Enter,
+            });
+        }
+
+        self.elapsed += delta;
+
+        let from = self.current;
+        let kind = match self.pending.take() {
+            Some(Pending::Enter(to)) => {
+                self.current = to;
+                self.elapsed = 0.0;
+                PhaseChangeKind::Enter
+            }
+            Some(Pending::Suspend(to)) => {
+                self.suspended = Some((from, self.elapsed));
+                self.current = to;
+                self.elapsed = 0.0;
+                PhaseChangeKind::Suspend
+            }
+            Some(Pending::Resume) => {
+                let (phase, elapsed) = self.suspended.take()?;
+                self.current = phase;
+                self.elapsed = elapsed;
+                PhaseChangeKind::Resume
+            }
+            None => {
+                let duration = self.duration(from)?;
+                if self.elapsed < duration {
+                    return None;
+                }
+                self.current = self
+                    .transitions
+                    .iter()
+                    .find(|t| t.from == from && t.trigger == Trigger::Timeout)?
+                    .to;
+                self.elapsed = 0.0;
+                PhaseChangeKind::Enter
+            }
+        };
+
+        Some(PhaseChanged {
+            from: Some(from),
+            to: self.current,
+            kind,
+        })
+    }
+}
@@ -1,72 +1,74 @@
 use bevy::prelude::*;
-use crate::plugins::game_state::events;
+use crate::constants;
+use crate::plugins::game_state::{events, machine::{Phase, PhaseMachine, Transition}};
 
-pub const PRE_GAME_DURATION: f32 = 3.0;
-pub const POST_GAME_DURATION: f32 = 4.0;
-
-pub struct PreGamePhase {
-    pub active: bool,
-    pub timer: Timer
+/// The phases a game cycles through
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum GamePhase {
+    /// The main menu, shown on launch and when the player leaves a finished game
+    Menu,
+    /// The board is set up and the snake waits to start moving
+    PreGame,
+    Running,
+    /// The run is over and its results are shown until the player restarts or goes back to the menu
+    PostGame,
+    /// A campaign level's goal was met, shown until the player moves on to the next level or goes back to the menu
+    LevelComplete,
+    /// Suspends whichever of the other phases was active
+    Paused,
 }
-impl PreGamePhase {
-    pub fn new(active: bool) -> Self {
-        PreGamePhase {
-            active,
-            timer: Timer::from_seconds(PRE_GAME_DURATION, false)
+impl Phase for GamePhase {
+    fn duration(&self) -> Option<f32> {
+        match self {
+            GamePhase::Menu => None,
+            // overridden by GameConfig::pre_game_seconds
+            GamePhase::PreGame => Some(constants::PRE_GAME_SECONDS),
+            GamePhase::Running => None,
+            GamePhase::PostGame => None,
+            GamePhase::LevelComplete => None,
+            GamePhase::Paused => None,
         }
     }
 }
-
-#[derive(Default)]
-pub struct PreGameStartListenerState {
-    pub event_reader: EventReader<events::PreGameStartEvent>
+impl GamePhase {
+    pub fn transitions() -> Vec<Transition<GamePhase>> {
+        vec![
+            Transition::request(GamePhase::Menu, GamePhase::PreGame),
+            Transition::timeout(GamePhase::PreGame, GamePhase::Running),
+            Transition::request(GamePhase::Running, GamePhase::PostGame),
+            Transition::request(GamePhase::PostGame, GamePhase::PreGame),
+            Transition::request(GamePhase::PostGame, GamePhase::Menu),
+            Transition::request(GamePhase::Running, GamePhase::LevelComplete),
+            Transition::request(GamePhase::LevelComplete, GamePhase::PreGame),
+            Transition::request(GamePhase::LevelComplete, GamePhase::Menu),
+            Transition::suspend(GamePhase::PreGame, GamePhase::Paused),
+            Transition::suspend(GamePhase::Running, GamePhase::Paused),
+            Transition::suspend(GamePhase::PostGame, GamePhase::Paused),
+        ]
+    }
 }
 
+pub type GamePhaseMachine = PhaseMachine<GamePhase>;
+pub type GamePhaseChanged = events::PhaseChanged<GamePhase>;
+
+/// How long the current run has been going, frozen once it ends and cleared when the next game is set up
 #[derive(Default)]
-pub struct PreGameEndListenerState {
-    pub event_reader: EventReader<events::PreGameEndEvent>
+pub struct RunClock {
+    pub elapsed: f32
 }
 
-pub struct RunningGamePhase {
-    pub active: bool
-}
-impl RunningGamePhase {
-    pub fn new(active: bool) -> Self {
-        RunningGamePhase {
-            active
-        }
-    }
+/// The last whole second announced by a [CountdownTickEvent](events::CountdownTickEvent), `None` outside the countdown
+#[derive(Default)]
+pub struct PreGameCountdown {
+    pub last: Option<u32>
 }
 
 #[derive(Default)]
-pub struct RunningGameStartListenerState {
-    pub event_reader: EventReader<events::RunningGameStartEvent>
+pub struct PhaseChangedListenerState {
+    pub event_reader: EventReader<GamePhaseChanged>
 }
 
 #[derive(Default)]
 pub struct RunningGameEndListenerState {
     pub event_reader: EventReader<events::RunningGameEndEvent>
 }
-
-pub struct PostGamePhase {
-    pub active: bool,
-    pub timer: Timer
-}
-impl PostGamePhase {
-    pub fn new(active: bool) -> Self {
-        PostGamePhase {
-            active,
-            timer: Timer::from_seconds(POST_GAME_DURATION, false)
-        }
-    }
-}
-
-#[derive(Default)]
-pub struct PostGameStartListenerState {
-    pub event_reader: EventReader<events::PostGameStartEvent>
-}
-
-#[derive(Default)]
-pub struct PostGameEndListenerState {
-    pub event_reader: EventReader<events::PostGameEndEvent>
-}
\ No newline at end of file
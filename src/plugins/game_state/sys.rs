@@ -1,99 +1,80 @@
 use bevy::prelude::*;
-use crate::plugins::game_state::{res, events};
+use crate::config::GameConfig;
+use crate::plugins::game_state::{res, events, machine::{Phase, PhaseMachine}};
 
-/// System that runs when the [PreGamePhase] is active. 
-///
-/// This will tick the phase's timer and fire events for different lifecycle states.
-/// ## Events
-/// - [PreGameStartEvent]
-/// - [PreGameEndEvent]
-pub fn pre_game_system(
+/// System that ticks a [PhaseMachine] and fires a [PhaseChanged](events::PhaseChanged) event whenever it
+/// enters a new phase.
+pub fn phase_machine_system<P: Phase>(
     time: Res<Time>,
-    mut start_events: ResMut<Events<events::PreGameStartEvent>>,
-    mut end_events: ResMut<Events<events::PreGameEndEvent>>,
-    mut pre_game_state: ResMut<res::PreGamePhase>
+    mut machine: ResMut<PhaseMachine<P>>,
+    mut phase_events: ResMut<Events<events::PhaseChanged<P>>>
 ) {
-    if !pre_game_state.active {
+    if time.delta_seconds == 0.0 {
         return;
     }
 
-    let timer = &mut pre_game_state.timer;
-
-    if timer.elapsed == 0.0 && time.delta_seconds != 0.0 {
-        start_events.send(events::PreGameStartEvent);
-    }
-
-    timer.tick(time.delta_seconds);
-
-    if timer.finished {
-        timer.reset();
-        pre_game_state.active = false;
-        end_events.send(events::PreGameEndEvent);
+    if let Some(event) = machine.tick(time.delta_seconds) {
+        phase_events.send(event);
     }
 }
 
-/// System that handles the active game phase when transitioning from [PreGamePhase] to [RunningGamePhase]
-pub fn pre_to_run_transition_system(
-    mut state: ResMut<res::PreGameEndListenerState>,
-    pre_game_end_events: Res<Events<events::PreGameEndEvent>>,
-    mut running_phase: ResMut<res::RunningGamePhase>,
-    mut running_game_start_events: ResMut<Events<events::RunningGameStartEvent>>
-) {
-    for _ in state.event_reader.iter(&pre_game_end_events) {
-        running_phase.active = true;
-        running_game_start_events.send(events::RunningGameStartEvent);
-    }
+/// Startup system that applies the phase lengths from the [GameConfig]
+pub fn apply_config_system(config: Res<GameConfig>, mut machine: ResMut<res::GamePhaseMachine>) {
+    machine.set_duration(res::GamePhase::PreGame, Some(config.pre_game_seconds));
 }
 
-pub fn run_to_post_transition_system(
-    mut state: ResMut<res::RunningGameEndListenerState>,
-    run_end_events: Res<Events<events::RunningGameEndEvent>>,
-    mut post_game_phase: ResMut<res::PostGamePhase>,
-    mut running_game_phase: ResMut<res::RunningGamePhase>
+/// System that fires a [CountdownTickEvent](events::CountdownTickEvent) each time the
+/// [GamePhase::PreGame](res::GamePhase::PreGame) timer passes a whole second, and once more as the run starts
+pub fn countdown_tick_system(
+    machine: Res<res::GamePhaseMachine>,
+    mut countdown: ResMut<res::PreGameCountdown>,
+    mut countdown_events: ResMut<Events<events::CountdownTickEvent>>
 ) {
-    for _ in state.event_reader.iter(&run_end_events) {
-        running_game_phase.active = false;
-        post_game_phase.active = true;
+    match machine.current() {
+        res::GamePhase::PreGame => {
+            let remaining = machine.remaining().unwrap_or(0.0).ceil() as u32;
+            if remaining > 0 && countdown.last != Some(remaining) {
+                countdown.last = Some(remaining);
+                countdown_events.send(events::CountdownTickEvent { remaining });
+            }
+        }
+        res::GamePhase::Running => {
+            if countdown.last.take().is_some() {
+                countdown_events.send(events::CountdownTickEvent { remaining: 0 });
+            }
+        }
+        _ => {}
     }
 }
 
-/// System that runs when the [PreGamePhase] is active. 
-///
-/// This will tick the phase's timer and fire events for different lifecycle states.
-/// ## Events
-/// - [PostGameStartEvent]
-/// - [PostGameEndEvent]
-pub fn post_game_system(
+/// System that counts up the [RunClock](res::RunClock) while [GamePhase::Running](res::GamePhase::Running) is active
+/// and clears it when [GamePhase::PreGame](res::GamePhase::PreGame) starts.
+pub fn run_clock_system(
     time: Res<Time>,
-    mut start_events: ResMut<Events<events::PostGameStartEvent>>,
-    mut end_events: ResMut<Events<events::PostGameEndEvent>>,
-    mut post_game_state: ResMut<res::PostGamePhase>
+    mut state: ResMut<res::PhaseChangedListenerState>,
+    phase_events: Res<Events<res::GamePhaseChanged>>,
+    machine: Res<res::GamePhaseMachine>,
+    mut clock: ResMut<res::RunClock>
 ) {
-    if !post_game_state.active {
-        return;
+    for event in state.event_reader.iter(&phase_events) {
+        if event.entered(res::GamePhase::PreGame) {
+            clock.elapsed = 0.0;
+        }
     }
 
-    let timer = &mut post_game_state.timer;
-
-    if timer.elapsed == 0.0 && time.delta_seconds != 0.0 {
-        start_events.send(events::PostGameStartEvent);
-    }
-
-    timer.tick(time.delta_seconds);
-
-    if timer.finished {
-        timer.reset();
-        post_game_state.active = false;
-        end_events.send(events::PostGameEndEvent);
+    if machine.is(res::GamePhase::Running) {
+        clock.elapsed += time.delta_seconds;
     }
 }
 
-pub fn post_to_pre_transition_system(
-    mut state: ResMut<res::PostGameEndListenerState>,
-    post_game_end_events: Res<Events<events::PostGameEndEvent>>,
-    mut pre_game_phase: ResMut<res::PreGamePhase>
+/// System that moves from [GamePhase::Running](res::GamePhase::Running) to [GamePhase::PostGame](res::GamePhase::PostGame)
+/// once the game logic fires a [RunningGameEndEvent](events::RunningGameEndEvent).
+pub fn run_to_post_transition_system(
+    mut state: ResMut<res::RunningGameEndListenerState>,
+    run_end_events: Res<Events<events::RunningGameEndEvent>>,
+    mut machine: ResMut<res::GamePhaseMachine>
 ) {
-    for _ in state.event_reader.iter(&post_game_end_events) {
-        pre_game_phase.active = true;
+    for _ in state.event_reader.iter(&run_end_events) {
+        machine.request(res::GamePhase::PostGame);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,26 @@
+use bevy::app::stage;
+use bevy::prelude::*;
+use gilrs::Gilrs;
+
+pub mod res;
+pub mod sys;
+
+/// Reads gamepads through gilrs, Bevy doesn't have gamepad input of its own yet.
+///
+/// Without any gamepad support on the system this only logs why, and [GamepadInput](res::GamepadInput)
+/// stays empty.
+pub struct GamepadPlugin;
+impl Plugin for GamepadPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_resource(res::GamepadInput::default());
+
+        match Gilrs::new() {
+            Ok(gilrs) => {
+                // gilrs has to stay on the thread it was created on
+                app.resources_mut().insert_thread_local(gilrs);
+                app.add_system_to_stage(stage::PRE_UPDATE, sys::gamepad_event_system.thread_local_system());
+            }
+            Err(e) => eprintln!("Gamepads unavailable: {}", e),
+        }
+    }
+}
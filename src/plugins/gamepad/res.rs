@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+
+use crate::comp::snake::SnakeDirection;
+
+/// The gamepad controls the game cares about
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GamepadButton {
+    /// A d-pad press, or the left stick leaning a new way
+    Turn(SnakeDirection),
+    Pause,
+    Forfeit,
+    /// Play again once a run is over, kept off [GamepadButton::Pause] so a pause can't restart by accident
+    Restart,
+}
+
+/// Which way a stick was last leaning, and where it is now
+#[derive(Debug, Default, Copy, Clone)]
+struct Stick {
+    x: f32,
+    y: f32,
+    direction: Option<SnakeDirection>,
+}
+
+/// Gamepad presses seen this frame, the gamepad counterpart of `Input<KeyCode>::just_pressed`
+#[derive(Default)]
+pub struct GamepadInput {
+    pressed: Vec<(usize, GamepadButton)>,
+    sticks: HashMap<usize, Stick>,
+}
+
+impl GamepadInput {
+    pub fn just_pressed(&self, gamepad: usize, button: GamepadButton) -> bool {
+        self.pressed.contains(&(gamepad, button))
+    }
+
+    /// Forgets last frame's presses, the sticks keep their position
+    pub fn clear(&mut self) {
+        self.pressed.clear();
+    }
+
+    pub fn press(&mut self, gamepad: usize, button: GamepadButton) {
+        self.pressed.push((gamepad, button));
+    }
+
+    /// Updates the left stick of `gamepad`, pressing a turn when it leans a new way past `dead_zone`.
+    ///
+    /// Only the axis that leans furthest counts, so diagonals pick whichever way is stronger.
+    pub fn move_stick(&mut self, gamepad: usize, x: Option<f32>, y: Option<f32>, dead_zone: f32) {
+        let stick = self.sticks.entry(gamepad).or_default();
+        stick.x = x.unwrap_or(stick.x);
+        stick.y = y.unwrap_or(stick.y);
+
+        let direction = if stick.x.abs().max(stick.y.abs()) < dead_zone {
+            None
+        } else if stick.x.abs() > stick.y.abs() {
+            Some(if stick.x > 0.0 { SnakeDirection::Right } else { SnakeDirection::Left })
+        } else {
+            Some(if stick.y > 0.0 { SnakeDirection::Up } else { SnakeDirection::Down })
+        };
+
+        let changed = direction != stick.direction;
+        stick.direction = direction;
+        if let (true, Some(direction)) = (changed, direction) {
+            self.press(gamepad, GamepadButton::Turn(direction));
+        }
+    }
+}
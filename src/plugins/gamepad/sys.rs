@@ -0,0 +1,45 @@
+use bevy::prelude::*;
+use gilrs::{Axis, Button, Event, EventType, Gilrs};
+
+use crate::comp::snake::SnakeDirection;
+use crate::plugins::gamepad::res::{GamepadButton, GamepadInput};
+use crate::settings::Settings;
+
+/// Drains the gilrs events into [GamepadInput] once per frame
+pub fn gamepad_event_system(_world: &mut World, resources: &mut Resources) {
+    let mut gilrs = resources.get_thread_local_mut::<Gilrs>().unwrap();
+    let mut input = resources.get_mut::<GamepadInput>().unwrap();
+    let dead_zone = resources.get::<Settings>().unwrap().stick_dead_zone;
+
+    input.clear();
+    while let Some(Event { id, event, .. }) = gilrs.next_event() {
+        let gamepad: usize = id.into();
+        match event {
+            EventType::ButtonPressed(button, _) => {
+                if let Some(button) = game_button(button) {
+                    input.press(gamepad, button);
+                }
+            }
+            EventType::AxisChanged(Axis::LeftStickX, value, _) => {
+                input.move_stick(gamepad, Some(value), None, dead_zone)
+            }
+            EventType::AxisChanged(Axis::LeftStickY, value, _) => {
+                input.move_stick(gamepad, None, Some(value), dead_zone)
+            }
+            _ => {}
+        }
+    }
+}
+
+fn game_button(button: Button) -> Option<GamepadButton> {
+    match button {
+        Button::DPadUp => Some(GamepadButton::Turn(SnakeDirection::Up)),
+        Button::DPadDown => Some(GamepadButton::Turn(SnakeDirection::Down)),
+        Button::DPadLeft => Some(GamepadButton::Turn(SnakeDirection::Left)),
+        Button::DPadRight => Some(GamepadButton::Turn(SnakeDirection::Right)),
+        Button::Start => Some(GamepadButton::Pause),
+        Button::Select => Some(GamepadButton::Forfeit),
+        Button::South => Some(GamepadButton::Restart),
+        _ => None,
+    }
+}
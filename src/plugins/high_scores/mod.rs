@@ -0,0 +1,19 @@
+use bevy::prelude::*;
+
+pub mod res;
+pub mod sys;
+
+/// Tag for the text rows of the post-game high-score table
+pub struct HighScoreText;
+
+pub struct HighScorePlugin;
+impl Plugin for HighScorePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app
+        .add_resource(res::HighScores::load_or_default())
+        .add_resource(res::RunningGameEndListenerState::default())
+        .add_resource(res::PhaseChangedListenerState::default())
+        .add_system(sys::record_high_score_system.system())
+        .add_system(sys::high_score_table_system.system());
+    }
+}
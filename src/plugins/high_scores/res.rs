@@ -0,0 +1,177 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::PathBuf;
+
+use crate::config::{GameConfig, GameMode};
+use crate::persist;
+use crate::sim::{FoodRules, SpeedCurve};
+use crate::plugins::game_state::{events, res::GamePhaseChanged};
+use crate::ui;
+
+/// How many runs are kept for each board configuration
+pub const HIGH_SCORES_PER_BOARD: usize = 10;
+
+/// The settings that make two runs comparable
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BoardConfig {
+    /// Size of the open board, 0 when a level is played since the level sets its own size
+    pub grid_size: i32,
+    #[serde(default)]
+    pub wrap_around: bool,
+    /// The level played, `None` for the open board
+    #[serde(default)]
+    pub level: Option<String>,
+    pub tick_seconds: f32,
+    #[serde(default)]
+    pub mode: GameMode,
+    #[serde(default = "one_player")]
+    pub players: usize,
+    /// Tables saved before the speed curve was added never sped up, which is the default
+    #[serde(default)]
+    pub speed: SpeedCurve,
+    /// Tables saved before food kinds were added only ever had normal food
+    #[serde(default = "FoodRules::classic")]
+    pub food: FoodRules,
+}
+
+/// Tables saved before multiplayer was added
+fn one_player() -> usize {
+    1
+}
+
+impl BoardConfig {
+    /// The board `config` sets up for `players` snakes, which is fewer than
+    /// [GameConfig::players] if the board has no room for them all
+    pub fn new(config: &GameConfig, players: usize) -> Self {
+        BoardConfig {
+            grid_size: if config.level.is_some() { 0 } else { config.grid_size },
+            wrap_around: config.wrap_around,
+            level: config.level.clone(),
+            tick_seconds: config.tick_seconds,
+            mode: config.mode,
+            players,
+            speed: config.speed,
+            food: config.food,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HighScoreEntry {
+    pub score: u32,
+    pub length: usize,
+    /// Seconds the run lasted
+    pub duration: f32,
+    pub seed: u64,
+    pub board: BoardConfig,
+}
+impl HighScoreEntry {
+    /// Whether this run ranks above `other`: higher score first, then the faster run
+    fn beats(&self, other: &HighScoreEntry) -> bool {
+        self.score > other.score || (self.score == other.score && self.duration < other.duration)
+    }
+}
+
+/// The local high-score file, plus the rank of the most recent run if it made the table
+pub struct HighScores {
+    pub entries: Vec<HighScoreEntry>,
+    pub last_rank: Option<usize>,
+    path: Option<PathBuf>,
+}
+
+impl HighScores {
+    /// `<user data dir>/bevy_snake/high_scores.ron`
+    pub fn default_path() -> Option<PathBuf> {
+        persist::user_file(dirs::data_dir(), "high_scores.ron")
+    }
+
+    /// Reads the table from [HighScores::default_path], starting empty if it doesn't exist yet
+    pub fn load_or_default() -> Self {
+        let path = HighScores::default_path();
+        let entries = persist::load_or_default(path.as_deref(), "high scores");
+        HighScores {
+            entries,
+            last_rank: None,
+            path,
+        }
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        persist::save(self.path.as_deref(), &self.entries)
+    }
+
+    /// Best runs on `board`, best first
+    pub fn top(&self, board: &BoardConfig) -> Vec<&HighScoreEntry> {
+        self.entries.iter().filter(|entry| entry.board == *board).collect()
+    }
+
+    /// Text rows listing `board`'s table, with the entry at `highlight` picked out
+    pub fn table_rows(&self, board: &BoardConfig, highlight: Option<usize>) -> Vec<(String, Color)> {
+        let mut rows = vec![(String::from("High scores"), Color::WHITE)];
+        if let Some(0) = highlight {
+            rows.push((String::from("New record!"), ui::HIGHLIGHT_COLOR));
+        }
+        for (rank, entry) in self.top(board).iter().enumerate() {
+            let color = if highlight == Some(rank) {
+                ui::HIGHLIGHT_COLOR
+            } else {
+                Color::WHITE
+            };
+            rows.push((
+                format!(
+                    "{:>2}. {:>4}  len {:>3}  {:>6.1}s  seed {}",
+                    rank + 1,
+                    entry.score,
+                    entry.length,
+                    entry.duration,
+                    entry.seed
+                ),
+                color,
+            ));
+        }
+        rows
+    }
+
+    /// Adds a finished run, dropping whatever falls off the bottom of its board's table.
+    ///
+    /// Returns the run's rank on its board if it was kept.
+    pub fn insert(&mut self, entry: HighScoreEntry) -> Option<usize> {
+        let board = entry.board.clone();
+        let index = self
+            .entries
+            .iter()
+            .position(|other| entry.beats(other))
+            .unwrap_or_else(|| self.entries.len());
+        self.entries.insert(index, entry);
+
+        let mut rank = None;
+        let mut kept = 0;
+        let mut i = 0;
+        self.entries.retain(|other| {
+            let is_new = i == index;
+            i += 1;
+            if other.board != board {
+                return true;
+            }
+            kept += 1;
+            if is_new && kept <= HIGH_SCORES_PER_BOARD {
+                rank = Some(kept - 1);
+            }
+            kept <= HIGH_SCORES_PER_BOARD
+        });
+
+        self.last_rank = rank;
+        rank
+    }
+}
+
+#[derive(Default)]
+pub struct RunningGameEndListenerState {
+    pub event_reader: EventReader<events::RunningGameEndEvent>
+}
+
+#[derive(Default)]
+pub struct PhaseChangedListenerState {
+    pub event_reader: EventReader<GamePhaseChanged>
+}
@@ -0,0 +1,85 @@
+use bevy::prelude::*;
+use crate::config::GameConfig;
+use crate::constants;
+use crate::plugins::game_state::{events, res::{GamePhase, GamePhaseChanged}};
+use crate::plugins::high_scores::{res, HighScoreText};
+use crate::sim::{GameRng, SnakeWorld};
+use crate::ui;
+use crate::ReplayMode;
+
+/// Adds the finished run to the [HighScores](res::HighScores) table and writes it to disk.
+///
+/// A multiplayer run counts once, with its best snake. Replays are skipped, they'd only duplicate
+/// the run they were recorded from.
+pub fn record_high_score_system(
+    mut state: ResMut<res::RunningGameEndListenerState>,
+    running_end_events: Res<Events<events::RunningGameEndEvent>>,
+    config: Res<GameConfig>,
+    world: Res<SnakeWorld>,
+    rng: Res<GameRng>,
+    replay_mode: Res<ReplayMode>,
+    mut high_scores: ResMut<res::HighScores>,
+) {
+    for event in state.event_reader.iter(&running_end_events) {
+        if let ReplayMode::Playing(_) = *replay_mode {
+            high_scores.last_rank = None;
+            continue;
+        }
+
+        let best = event
+            .results
+            .iter()
+            .map(|result| result.stats)
+            .max_by(|a, b| a.score.cmp(&b.score).then(b.duration.partial_cmp(&a.duration).unwrap()));
+        let stats = match best {
+            Some(stats) => stats,
+            None => continue,
+        };
+
+        high_scores.insert(res::HighScoreEntry {
+            score: stats.score,
+            length: stats.length,
+            duration: stats.duration,
+            seed: rng.seed(),
+            board: res::BoardConfig::new(&config, world.snakes.len()),
+        });
+
+        if let Err(e) = high_scores.save() {
+            eprintln!("Could not save high scores: {}", e);
+        }
+    }
+}
+
+/// Lists the current board's high scores while in [GamePhase::PostGame],
+/// with the run that just finished highlighted if it made the table.
+pub fn high_score_table_system(
+    mut commands: Commands,
+    mut state: ResMut<res::PhaseChangedListenerState>,
+    phase_events: Res<Events<GamePhaseChanged>>,
+    asset_server: Res<AssetServer>,
+    config: Res<GameConfig>,
+    world: Res<SnakeWorld>,
+    high_scores: Res<res::HighScores>,
+    mut text_query: Query<(&HighScoreText, Entity)>,
+) {
+    for event in state.event_reader.iter(&phase_events) {
+        if event.exited(GamePhase::PostGame) {
+            for (_, e) in &mut text_query.iter() {
+                commands.despawn(e);
+            }
+        }
+        if !event.entered(GamePhase::PostGame) {
+            continue;
+        }
+
+        let font = asset_server.load(constants::UI_FONT).unwrap();
+        let board = res::BoardConfig::new(&config, world.snakes.len());
+
+        let rows = high_scores.table_rows(&board, high_scores.last_rank);
+        for (i, (value, color)) in rows.into_iter().enumerate() {
+            commands
+                .spawn(ui::text(font, value, 20.0, color, ui::top_left(10.0, 50.0 + 24.0 * i as f32)))
+                .with(HighScoreText);
+        }
+    }
+}
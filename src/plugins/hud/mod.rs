@@ -0,0 +1,27 @@
+use bevy::prelude::*;
+
+pub mod res;
+pub mod sys;
+
+/// Tag for the text node showing the running game's stats
+pub struct HudText;
+
+/// Tag for the text node explaining why the last run ended
+pub struct GameOverText;
+
+/// Tag for the big "3, 2, 1, Go" text in the middle of the screen
+pub struct CountdownText;
+
+pub struct HudPlugin;
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app
+        .add_resource(res::RunningGameEndListenerState::default())
+        .add_resource(res::PhaseChangedListenerState::default())
+        .add_startup_system(sys::setup_hud_system.system())
+        .add_system(sys::hud_text_system.system())
+        .add_system(sys::countdown_text_system.system())
+        .add_system(sys::show_game_over_system.system())
+        .add_system(sys::hide_game_over_system.system());
+    }
+}
@@ -0,0 +1,15 @@
+use bevy::prelude::*;
+use crate::plugins::game_state::{events, res::GamePhaseChanged};
+
+/// Seconds "Go" stays up after the countdown
+pub const GO_TEXT_SECONDS: f32 = 0.6;
+
+#[derive(Default)]
+pub struct RunningGameEndListenerState {
+    pub event_reader: EventReader<events::RunningGameEndEvent>
+}
+
+#[derive(Default)]
+pub struct PhaseChangedListenerState {
+    pub event_reader: EventReader<GamePhaseChanged>
+}
@@ -0,0 +1,152 @@
+use bevy::prelude::*;
+use crate::comp::snake::{Score, Snake};
+use crate::config::{GameConfig, GameMode};
+use crate::constants;
+use crate::plugins::game_state::{events, res::{GamePhase, GamePhaseChanged, GamePhaseMachine, RunClock}};
+use crate::plugins::hud::{res, CountdownText, GameOverText, HudText};
+use crate::sim::Levels;
+use crate::ui;
+
+/// Spawns the HUD text in the top left corner
+pub fn setup_hud_system(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font = asset_server.load(constants::UI_FONT).unwrap();
+
+    commands
+        .spawn(ui::text(font, "", 24.0, Color::WHITE, ui::top_left(10.0, 10.0)))
+        .with(HudText);
+
+    let position = Rect {
+        left: Val::Percent(47.0),
+        top: Val::Percent(40.0),
+        ..Default::default()
+    };
+    commands
+        .spawn(ui::text(font, "", 72.0, ui::HIGHLIGHT_COLOR, position))
+        .with(CountdownText);
+}
+
+/// Counts down the seconds left in [GamePhase::PreGame], then briefly shows "Go" once the run starts
+pub fn countdown_text_system(
+    machine: Res<GamePhaseMachine>,
+    run_clock: Res<RunClock>,
+    mut text_query: Query<(&mut Text, &CountdownText)>,
+) {
+    let value = match machine.current() {
+        GamePhase::PreGame => match machine.remaining() {
+            Some(remaining) if remaining > 0.0 => remaining.ceil().to_string(),
+            _ => String::new(),
+        },
+        GamePhase::Running if run_clock.elapsed < res::GO_TEXT_SECONDS => String::from("Go"),
+        _ => String::new(),
+    };
+
+    for (mut text, _) in &mut text_query.iter() {
+        if text.value != value {
+            text.value = value.clone();
+        }
+    }
+}
+
+/// Writes the current score, length, speed and elapsed time into the HUD, plus the level's goal in the campaign.
+///
+/// The values come straight from the live [Snake] and [RunClock], so they reset along with them
+/// when a new game is spawned on entering [GamePhase::PreGame].
+pub fn hud_text_system(
+    config: Res<GameConfig>,
+    levels: Res<Levels>,
+    run_clock: Res<RunClock>,
+    machine: Res<GamePhaseMachine>,
+    mut snake_query: Query<(&Snake, &Score)>,
+    mut text_query: Query<(&mut Text, &HudText)>,
+) {
+    // the menu draws over an empty board
+    if machine.is(GamePhase::Menu) {
+        for (mut text, _) in &mut text_query.iter() {
+            text.value.clear();
+        }
+        return;
+    }
+
+    // speed in steps per second
+    let mut players: Vec<(usize, u32, usize, f32)> = Vec::new();
+    for (snake, score) in &mut snake_query.iter() {
+        players.push((snake.player, score.0, snake.body.len(), 1.0 / snake.step_seconds));
+    }
+    players.sort_by_key(|(player, ..)| *player);
+
+    let mut value = match players.as_slice() {
+        [] => String::new(),
+        [(_, score, length, speed)] => format!(
+            "Score: {}  Length: {}  Speed: {:.1}/s  Time: {:.1}s",
+            score, length, speed, run_clock.elapsed
+        ),
+        _ => {
+            let mut value = format!("Time: {:.1}s", run_clock.elapsed);
+            for (player, score, length, speed) in players.iter() {
+                value.push_str(&format!("  P{}: {} ({} long, {:.1}/s)", player + 1, score, length, speed));
+            }
+            value
+        }
+    };
+    if config.mode == GameMode::Campaign {
+        if let Some(goal) = config.level.as_ref().and_then(|name| levels.find(name)).and_then(|level| level.goal) {
+            value.push_str(&format!("  Goal: {}", goal));
+        }
+    }
+
+    for (mut text, _) in &mut text_query.iter() {
+        text.value = value.clone();
+    }
+}
+
+/// Shows why the run ended while in [GamePhase::PostGame]
+pub fn show_game_over_system(
+    mut commands: Commands,
+    mut state: ResMut<res::RunningGameEndListenerState>,
+    running_end_events: Res<Events<events::RunningGameEndEvent>>,
+    asset_server: Res<AssetServer>,
+) {
+    for event in state.event_reader.iter(&running_end_events) {
+        let font = asset_server.load(constants::UI_FONT).unwrap();
+        let multiplayer = event.results.len() > 1;
+
+        for (i, result) in event.results.iter().enumerate() {
+            let color = if result.reason.is_victory() {
+                ui::HIGHLIGHT_COLOR
+            } else {
+                Color::rgb(1.0, 0.3, 0.3)
+            };
+            let value = if multiplayer {
+                format!("P{}: {}", result.player + 1, result.reason)
+            } else {
+                result.reason.to_string()
+            };
+
+            let position = Rect {
+                right: Val::Px(10.0),
+                top: Val::Px(10.0 + 30.0 * i as f32),
+                ..Default::default()
+            };
+            commands
+                .spawn(ui::text(font, value, 24.0, color, position))
+                .with(GameOverText);
+        }
+    }
+}
+
+/// Clears the game over text when the next game is set up or the player goes back to the menu
+pub fn hide_game_over_system(
+    mut commands: Commands,
+    mut state: ResMut<res::PhaseChangedListenerState>,
+    phase_events: Res<Events<GamePhaseChanged>>,
+    mut text_query: Query<(&GameOverText, Entity)>,
+) {
+    for event in state.event_reader.iter(&phase_events) {
+        if !event.entered(GamePhase::PreGame) && !event.entered(GamePhase::Menu) {
+            continue;
+        }
+        for (_, e) in &mut text_query.iter() {
+            commands.despawn(e);
+        }
+    }
+}
@@ -0,0 +1,23 @@
+use bevy::prelude::*;
+
+pub mod res;
+pub mod sys;
+
+/// Tag for the rows of the menu screen currently shown
+pub struct MenuText;
+
+/// Tag for the restart/back hint shown after a run
+pub struct PostGameText;
+
+pub struct MenuPlugin;
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app
+        .add_resource(res::MenuState::default())
+        .add_resource(res::PhaseChangedListenerState::default())
+        .add_system(sys::menu_input_system.system())
+        .add_system(sys::menu_render_system.system())
+        .add_system(sys::post_game_choice_system.system())
+        .add_system(sys::post_game_hint_system.system());
+    }
+}
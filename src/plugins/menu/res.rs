@@ -0,0 +1,87 @@
+use bevy::prelude::*;
+use crate::plugins::game_state::res::GamePhaseChanged;
+
+/// Board sizes the menu cycles through, as [GameConfig::grid_size](crate::config::GameConfig::grid_size).
+///
+/// The largest is 21 cells across, which with its walls still fits the default 1280x720 window at the
+/// default [GRID_UNIT](crate::constants::GRID_UNIT).
+pub const BOARD_SIZES: [i32; 4] = [3, 5, 8, 10];
+
+/// Entries of the main menu, top to bottom
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MenuItem {
+    Play,
+    Mode,
+    Level,
+    BoardSize,
+    Edges,
+    Players,
+    Controls,
+    HighScores,
+    Quit,
+}
+impl MenuItem {
+    pub const ALL: [MenuItem; 9] = [
+        MenuItem::Play,
+        MenuItem::Mode,
+        MenuItem::Level,
+        MenuItem::BoardSize,
+        MenuItem::Edges,
+        MenuItem::Players,
+        MenuItem::Controls,
+        MenuItem::HighScores,
+        MenuItem::Quit,
+    ];
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MenuScreen {
+    Main,
+    /// Which device each player steers with
+    Controls,
+    /// The keys of the player picked on [MenuScreen::Controls]
+    Rebind,
+    /// The high score table for the board picked in the menu
+    HighScores,
+}
+
+pub struct MenuState {
+    pub screen: MenuScreen,
+    /// Index into [MenuItem::ALL]
+    pub selected: usize,
+    /// The player picked on the [MenuScreen::Controls] screen
+    pub control_row: usize,
+    /// Index into [KeyAction::ALL](crate::comp::input::KeyAction::ALL) on the [MenuScreen::Rebind] screen
+    pub action_row: usize,
+    /// Waiting for the key to bind to the selected action
+    pub capturing: bool,
+    /// Why the last key pressed couldn't be bound
+    pub message: Option<String>,
+    /// Set whenever the shown text is out of date
+    pub dirty: bool,
+}
+
+impl Default for MenuState {
+    fn default() -> Self {
+        MenuState {
+            screen: MenuScreen::Main,
+            selected: 0,
+            control_row: 0,
+            action_row: 0,
+            capturing: false,
+            message: None,
+            dirty: true,
+        }
+    }
+}
+
+impl MenuState {
+    pub fn selected_item(&self) -> MenuItem {
+        MenuItem::ALL[self.selected]
+    }
+}
+
+#[derive(Default)]
+pub struct PhaseChangedListenerState {
+    pub event_reader: EventReader<GamePhaseChanged>
+}
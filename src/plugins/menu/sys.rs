@@ -0,0 +1,445 @@
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use crate::config::{GameConfig, GameMode, MAX_PLAYERS};
+use crate::constants;
+use crate::plugins::campaign::res::CampaignProgress;
+use crate::plugins::gamepad::res::GamepadInput;
+use crate::plugins::game_state::res::{GamePhase, GamePhaseChanged, GamePhaseMachine};
+use crate::plugins::high_scores::res::{BoardConfig, HighScores};
+use crate::plugins::menu::{res, MenuText, PostGameText};
+use crate::comp::input::{InputBinding, KeyAction};
+use crate::settings::{InputDevice, Settings};
+use crate::sim::{Levels, SnakeWorld};
+use crate::ui;
+use crate::{ReplayMode, PLAYER_COLORS};
+
+/// Moves through the menu and acts on the selected entry
+pub fn menu_input_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    replay_mode: Res<ReplayMode>,
+    mut machine: ResMut<GamePhaseMachine>,
+    mut menu: ResMut<res::MenuState>,
+    mut config: ResMut<GameConfig>,
+    mut settings: ResMut<Settings>,
+    levels: Res<Levels>,
+    campaign: Res<CampaignProgress>,
+    mut exit_events: ResMut<Events<AppExit>>,
+) {
+    if !machine.is(GamePhase::Menu) {
+        return;
+    }
+
+    match menu.screen {
+        res::MenuScreen::Controls => return controls_input(&keyboard_input, &mut menu, &mut settings),
+        res::MenuScreen::Rebind => return rebind_input(&keyboard_input, &mut menu, &mut settings),
+        _ => {}
+    }
+    if menu.screen != res::MenuScreen::Main {
+        if keyboard_input.just_pressed(KeyCode::Escape) || keyboard_input.just_pressed(KeyCode::Return) {
+            menu.screen = res::MenuScreen::Main;
+            menu.dirty = true;
+        }
+        return;
+    }
+
+    let count = res::MenuItem::ALL.len();
+    if keyboard_input.just_pressed(KeyCode::Up) {
+        menu.selected = (menu.selected + count - 1) % count;
+        menu.dirty = true;
+    }
+    if keyboard_input.just_pressed(KeyCode::Down) {
+        menu.selected = (menu.selected + 1) % count;
+        menu.dirty = true;
+    }
+
+    let forward = keyboard_input.just_pressed(KeyCode::Right) || keyboard_input.just_pressed(KeyCode::Return);
+    let back = keyboard_input.just_pressed(KeyCode::Left);
+    if !forward && !back {
+        return;
+    }
+
+    let replaying = matches!(*replay_mode, ReplayMode::Playing(_));
+    match menu.selected_item() {
+        res::MenuItem::Mode => {
+            // the mode decides when a replayed run ends
+            if replaying {
+                return;
+            }
+            config.mode = cycle_mode(config.mode, forward);
+            if config.mode == GameMode::Campaign {
+                config.level = campaign.current(&levels).map(|level| level.name.clone());
+            }
+            menu.dirty = true;
+        }
+        res::MenuItem::Level => {
+            // a replay only makes sense on the board and with the players it was recorded with
+            if replaying {
+                return;
+            }
+            // the campaign picks its own levels
+            if config.mode == GameMode::Campaign {
+                return;
+            }
+            config.level = cycle_level(&levels, config.level.as_deref(), forward);
+            menu.dirty = true;
+        }
+        res::MenuItem::BoardSize => {
+            // a level brings its own size
+            if config.level.is_some() {
+                return;
+            }
+            if replaying {
+                return;
+            }
+            config.grid_size = cycle_board_size(config.grid_size, forward);
+            menu.dirty = true;
+        }
+        res::MenuItem::Edges => {
+            if replaying {
+                return;
+            }
+            config.wrap_around = !config.wrap_around;
+            menu.dirty = true;
+        }
+        res::MenuItem::Players => {
+            if replaying {
+                return;
+            }
+            config.players = if forward {
+                config.players % MAX_PLAYERS + 1
+            } else {
+                (config.players + MAX_PLAYERS - 2) % MAX_PLAYERS + 1
+            };
+            menu.dirty = true;
+        }
+        _ if !keyboard_input.just_pressed(KeyCode::Return) => {}
+        res::MenuItem::Play => {
+            if config.mode == GameMode::Campaign && !replaying {
+                config.level = campaign.current(&levels).map(|level| level.name.clone());
+            }
+            machine.request(GamePhase::PreGame);
+        }
+        res::MenuItem::Controls => {
+            menu.screen = res::MenuScreen::Controls;
+            menu.dirty = true;
+        }
+        res::MenuItem::HighScores => {
+            menu.screen = res::MenuScreen::HighScores;
+            menu.dirty = true;
+        }
+        res::MenuItem::Quit => exit_events.send(AppExit),
+    }
+}
+
+/// Up and down pick a player, left and right change their device, which is saved straight away.
+/// Enter opens the player's keys.
+fn controls_input(keyboard_input: &Input<KeyCode>, menu: &mut res::MenuState, settings: &mut Settings) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        menu.screen = res::MenuScreen::Main;
+        menu.dirty = true;
+        return;
+    }
+    if keyboard_input.just_pressed(KeyCode::Return) {
+        menu.screen = res::MenuScreen::Rebind;
+        menu.action_row = 0;
+        menu.message = None;
+        menu.dirty = true;
+        return;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Up) {
+        menu.control_row = (menu.control_row + MAX_PLAYERS - 1) % MAX_PLAYERS;
+        menu.dirty = true;
+    }
+    if keyboard_input.just_pressed(KeyCode::Down) {
+        menu.control_row = (menu.control_row + 1) % MAX_PLAYERS;
+        menu.dirty = true;
+    }
+
+    let forward = keyboard_input.just_pressed(KeyCode::Right);
+    if forward || keyboard_input.just_pressed(KeyCode::Left) {
+        let player = menu.control_row;
+        settings.set_device(player, settings.device(player).cycle(forward));
+        if let Err(e) = settings.save() {
+            eprintln!("Could not save settings: {}", e);
+        }
+        menu.dirty = true;
+    }
+}
+
+/// Up and down pick an action and Enter waits for the key to bind to it, which is saved straight away
+fn rebind_input(keyboard_input: &Input<KeyCode>, menu: &mut res::MenuState, settings: &mut Settings) {
+    if menu.capturing {
+        let key = match keyboard_input.get_just_pressed().next() {
+            Some(key) => *key,
+            None => return,
+        };
+        menu.capturing = false;
+        menu.dirty = true;
+        if key == KeyCode::Escape {
+            menu.message = None;
+            return;
+        }
+
+        let action = KeyAction::ALL[menu.action_row];
+        menu.message = match settings.rebind(menu.control_row, action, key) {
+            Ok(()) => {
+                if let Err(e) = settings.save() {
+                    eprintln!("Could not save settings: {}", e);
+                }
+                None
+            }
+            Err(reason) => Some(reason),
+        };
+        return;
+    }
+
+    let count = KeyAction::ALL.len();
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        menu.screen = res::MenuScreen::Controls;
+        menu.message = None;
+        menu.dirty = true;
+    } else if keyboard_input.just_pressed(KeyCode::Return) {
+        menu.capturing = true;
+        menu.message = None;
+        menu.dirty = true;
+    } else if keyboard_input.just_pressed(KeyCode::Up) {
+        menu.action_row = (menu.action_row + count - 1) % count;
+        menu.dirty = true;
+    } else if keyboard_input.just_pressed(KeyCode::Down) {
+        menu.action_row = (menu.action_row + 1) % count;
+        menu.dirty = true;
+    }
+}
+
+fn cycle_mode(mode: GameMode, forward: bool) -> GameMode {
+    let count = GameMode::ALL.len();
+    let i = GameMode::ALL.iter().position(|m| *m == mode).unwrap_or(0);
+    let i = if forward { (i + 1) % count } else { (i + count - 1) % count };
+    GameMode::ALL[i]
+}
+
+/// Steps through the open board (`None`) followed by every level
+fn cycle_level(levels: &Levels, current: Option<&str>, forward: bool) -> Option<String> {
+    let count = levels.0.len() + 1;
+    let index = current
+        .and_then(|name| levels.0.iter().position(|level| level.name == name))
+        .map_or(0, |index| index + 1);
+    let index = if forward { (index + 1) % count } else { (index + count - 1) % count };
+    index.checked_sub(1).map(|index| levels.0[index].name.clone())
+}
+
+/// The next of [BOARD_SIZES](res::BOARD_SIZES) up or down from `grid_size`, wrapping around at either end.
+///
+/// `grid_size` needn't be one of them, it may have come from the config file or command line.
+fn cycle_board_size(grid_size: i32, forward: bool) -> i32 {
+    let sizes = &res::BOARD_SIZES;
+    if forward {
+        *sizes.iter().find(|s| **s > grid_size).unwrap_or(&sizes[0])
+    } else {
+        *sizes.iter().rev().find(|s| **s < grid_size).unwrap_or(&sizes[sizes.len() - 1])
+    }
+}
+
+/// Redraws the current menu screen whenever it changes, and clears it once the game starts
+pub fn menu_render_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    machine: Res<GamePhaseMachine>,
+    config: Res<GameConfig>,
+    settings: Res<Settings>,
+    levels: Res<Levels>,
+    campaign: Res<CampaignProgress>,
+    high_scores: Res<HighScores>,
+    mut menu: ResMut<res::MenuState>,
+    mut text_query: Query<(&MenuText, Entity)>,
+) {
+    if !machine.is(GamePhase::Menu) {
+        for (_, e) in &mut text_query.iter() {
+            commands.despawn(e);
+        }
+        menu.dirty = true;
+        return;
+    }
+    if !menu.dirty {
+        return;
+    }
+    menu.dirty = false;
+
+    for (_, e) in &mut text_query.iter() {
+        commands.despawn(e);
+    }
+
+    let rows = match menu.screen {
+        res::MenuScreen::Main => main_rows(&menu, &config, &levels, &campaign),
+        res::MenuScreen::Controls => control_rows(&menu, &settings),
+        res::MenuScreen::Rebind => rebind_rows(&menu, &settings),
+        res::MenuScreen::HighScores => {
+            let level = config.level.as_ref().and_then(|name| levels.find(name));
+            let players = SnakeWorld::player_count(config.arena(), level, config.players);
+            high_scores.table_rows(&BoardConfig::new(&config, players), None)
+        }
+    };
+
+    let font = asset_server.load(constants::UI_FONT).unwrap();
+    commands
+        .spawn(ui::text(font, "Snake", 48.0, Color::WHITE, ui::top_left(40.0, 30.0)))
+        .with(MenuText);
+    for (i, (value, color)) in rows.into_iter().enumerate() {
+        commands
+            .spawn(ui::text(font, value, 28.0, color, ui::top_left(40.0, 100.0 + 36.0 * i as f32)))
+            .with(MenuText);
+    }
+}
+
+fn main_rows(
+    menu: &res::MenuState,
+    config: &GameConfig,
+    levels: &Levels,
+    progress: &CampaignProgress,
+) -> Vec<(String, Color)> {
+    let level = config.level.as_ref().and_then(|name| levels.find(name));
+    let board_size = match level {
+        Some(level) => format!("Board size: {}x{} (level)", level.arena.width(), level.arena.height()),
+        None => {
+            let cells = config.grid_size * 2 + 1;
+            format!("Board size: < {}x{} >", cells, cells)
+        }
+    };
+    res::MenuItem::ALL
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let label = match item {
+                res::MenuItem::Play => String::from("Play"),
+                res::MenuItem::Mode => format!("Mode: < {} >", config.mode.name()),
+                res::MenuItem::Level if config.mode == GameMode::Campaign => {
+                    let campaign = levels.campaign();
+                    let position = campaign.iter().position(|other| Some(other.name.as_str()) == config.level.as_deref());
+                    match (level, position) {
+                        (Some(level), Some(position)) => {
+                            let finished = if progress.finished { ", campaign complete" } else { "" };
+                            format!("Level: {} ({}/{}{})", level.name, position + 1, campaign.len(), finished)
+                        }
+                        _ => String::from("Level: none"),
+                    }
+                }
+                res::MenuItem::Level => format!("Level: < {} >", level.map_or("Open board", |level| level.name.as_str())),
+                res::MenuItem::BoardSize => board_size.clone(),
+                res::MenuItem::Edges => {
+                    format!("Edges: < {} >", if config.wrap_around { "Wrap around" } else { "Walls" })
+                }
+                res::MenuItem::Players => format!("Players: < {} >", config.players),
+                res::MenuItem::Controls => String::from("Controls"),
+                res::MenuItem::HighScores => String::from("High scores"),
+                res::MenuItem::Quit => String::from("Quit"),
+            };
+            let color = if i == menu.selected {
+                ui::HIGHLIGHT_COLOR
+            } else {
+                Color::WHITE
+            };
+            (label, color)
+        })
+        .collect()
+}
+
+fn control_rows(menu: &res::MenuState, settings: &Settings) -> Vec<(String, Color)> {
+    (0..MAX_PLAYERS)
+        .map(|player| {
+            let controls = match settings.device(player) {
+                InputDevice::Keyboard => {
+                    let keys = settings.keys(player);
+                    format!(
+                        "Keyboard: {:?} {:?} {:?} {:?}  Forfeit: {:?}  Pause: {:?}  Restart: {:?}",
+                        keys.up, keys.down, keys.left, keys.right, keys.forfeit, keys.pause, keys.restart
+                    )
+                }
+                InputDevice::Gamepad(gamepad) => format!(
+                    "Gamepad {}: d-pad or left stick  Forfeit: Select  Pause: Start  Restart: A",
+                    gamepad + 1
+                ),
+            };
+            let color = if player == menu.control_row {
+                ui::HIGHLIGHT_COLOR
+            } else {
+                PLAYER_COLORS[player]
+            };
+            (format!("P{}  < {} >", player + 1, controls), color)
+        })
+        .collect()
+}
+
+fn rebind_rows(menu: &res::MenuState, settings: &Settings) -> Vec<(String, Color)> {
+    let player = menu.control_row;
+    let keys = settings.keys(player);
+
+    let mut rows = vec![(format!("P{} keys", player + 1), PLAYER_COLORS[player])];
+    for (i, action) in KeyAction::ALL.iter().enumerate() {
+        let selected = i == menu.action_row;
+        let key = if selected && menu.capturing {
+            String::from("press a key, Escape to cancel")
+        } else {
+            format!("{:?}", keys.get(*action))
+        };
+        let color = if selected { ui::HIGHLIGHT_COLOR } else { Color::WHITE };
+        rows.push((format!("{:<8} {}", action.name(), key), color));
+    }
+    if let Some(message) = &menu.message {
+        rows.push((message.clone(), Color::rgb(1.0, 0.3, 0.3)));
+    }
+    rows
+}
+
+/// Restarts on Enter or any player's restart key or button, or goes back to the menu on Escape,
+/// once the players have seen how the run went
+pub fn post_game_choice_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepad_input: Res<GamepadInput>,
+    world: Res<SnakeWorld>,
+    settings: Res<Settings>,
+    mut machine: ResMut<GamePhaseMachine>,
+) {
+    if !machine.is(GamePhase::PostGame) {
+        return;
+    }
+
+    let restart = (0..world.snakes.len())
+        .any(|player| InputBinding::for_player(player, &settings).restart_pressed(&keyboard_input, &gamepad_input));
+    if keyboard_input.just_pressed(KeyCode::Return) || restart {
+        machine.request(GamePhase::PreGame);
+    } else if keyboard_input.just_pressed(KeyCode::Escape) {
+        machine.request(GamePhase::Menu);
+    }
+}
+
+/// Tells the player how to carry on while in [GamePhase::PostGame]
+pub fn post_game_hint_system(
+    mut commands: Commands,
+    mut state: ResMut<res::PhaseChangedListenerState>,
+    phase_events: Res<Events<GamePhaseChanged>>,
+    asset_server: Res<AssetServer>,
+    mut text_query: Query<(&PostGameText, Entity)>,
+) {
+    for event in state.event_reader.iter(&phase_events) {
+        if event.exited(GamePhase::PostGame) {
+            for (_, e) in &mut text_query.iter() {
+                commands.despawn(e);
+            }
+        }
+        if !event.entered(GamePhase::PostGame) {
+            continue;
+        }
+
+        let font = asset_server.load(constants::UI_FONT).unwrap();
+        // bottom right, clear of the pause overlay
+        let position = Rect {
+            right: Val::Px(10.0),
+            bottom: Val::Px(10.0),
+            ..Default::default()
+        };
+        commands
+            .spawn(ui::text(font, "Enter or Restart: play again   Esc: menu", 24.0, Color::WHITE, position))
+            .with(PostGameText);
+    }
+}
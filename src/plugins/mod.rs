@@ -1 +1,7 @@
-pub mod game_state;
\ No newline at end of file
+pub mod campaign;
+pub mod game_state;
+pub mod gamepad;
+pub mod high_scores;
+pub mod hud;
+pub mod menu;
+pub mod pause;
\ No newline at end of file
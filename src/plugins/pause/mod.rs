@@ -0,0 +1,19 @@
+use bevy::prelude::*;
+
+pub mod res;
+pub mod sys;
+
+/// Tag for the text shown over the board while paused
+pub struct PauseText;
+
+pub struct PausePlugin;
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app
+        .add_resource(res::ResumeCountdown::default())
+        .add_resource(res::PhaseChangedListenerState::default())
+        .add_system(sys::pause_input_system.system())
+        .add_system(sys::resume_countdown_system.system())
+        .add_system(sys::pause_overlay_system.system());
+    }
+}
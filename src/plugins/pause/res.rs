@@ -0,0 +1,14 @@
+use bevy::prelude::*;
+use crate::plugins::game_state::res::GamePhaseChanged;
+
+/// Seconds between unpausing and the game picking back up
+pub const RESUME_COUNTDOWN: f32 = 3.0;
+
+/// Counts down to resuming once the player unpauses, `None` while waiting for them to do so
+#[derive(Default)]
+pub struct ResumeCountdown(pub Option<Timer>);
+
+#[derive(Default)]
+pub struct PhaseChangedListenerState {
+    pub event_reader: EventReader<GamePhaseChanged>
+}
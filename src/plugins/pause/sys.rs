@@ -0,0 +1,89 @@
+use bevy::prelude::*;
+use crate::constants;
+use crate::plugins::game_state::res::{GamePhase, GamePhaseChanged, GamePhaseMachine};
+use crate::plugins::pause::{res, PauseText};
+use crate::ui;
+use crate::comp::input::InputBinding;
+use crate::plugins::gamepad::res::GamepadInput;
+
+/// Pauses the game when any player presses their pause key or button, and starts the resume countdown
+/// when one is pressed again while paused.
+pub fn pause_input_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepad_input: Res<GamepadInput>,
+    mut machine: ResMut<GamePhaseMachine>,
+    mut countdown: ResMut<res::ResumeCountdown>,
+    mut binding_query: Query<&InputBinding>,
+) {
+    let mut pressed = false;
+    for binding in &mut binding_query.iter() {
+        pressed |= binding.pause_pressed(&keyboard_input, &gamepad_input);
+    }
+    if !pressed {
+        return;
+    }
+
+    if !machine.is(GamePhase::Paused) {
+        machine.request(GamePhase::Paused);
+    } else if countdown.0.is_none() {
+        countdown.0 = Some(Timer::from_seconds(res::RESUME_COUNTDOWN, false));
+    }
+}
+
+/// Resumes the suspended phase once the [ResumeCountdown](res::ResumeCountdown) runs out
+pub fn resume_countdown_system(
+    time: Res<Time>,
+    mut machine: ResMut<GamePhaseMachine>,
+    mut countdown: ResMut<res::ResumeCountdown>,
+) {
+    let finished = match &mut countdown.0 {
+        Some(timer) => {
+            timer.tick(time.delta_seconds);
+            timer.finished
+        }
+        None => return,
+    };
+
+    if finished {
+        countdown.0 = None;
+        machine.resume();
+    }
+}
+
+/// Shows the pause text for the length of [GamePhase::Paused], counting down once the player unpauses
+pub fn pause_overlay_system(
+    mut commands: Commands,
+    mut state: ResMut<res::PhaseChangedListenerState>,
+    phase_events: Res<Events<GamePhaseChanged>>,
+    asset_server: Res<AssetServer>,
+    countdown: Res<res::ResumeCountdown>,
+    mut text_query: Query<(&mut Text, &PauseText, Entity)>,
+) {
+    for event in state.event_reader.iter(&phase_events) {
+        if event.exited(GamePhase::Paused) {
+            for (_, _, e) in &mut text_query.iter() {
+                commands.despawn(e);
+            }
+        }
+        if !event.entered(GamePhase::Paused) {
+            continue;
+        }
+
+        let font = asset_server.load(constants::UI_FONT).unwrap();
+        let position = Rect {
+            left: Val::Px(10.0),
+            bottom: Val::Px(10.0),
+            ..Default::default()
+        };
+        commands
+            .spawn(ui::text(font, "", 32.0, Color::WHITE, position))
+            .with(PauseText);
+    }
+
+    for (mut text, _, _) in &mut text_query.iter() {
+        text.value = match &countdown.0 {
+            Some(timer) => format!("Resuming in {}", (timer.duration - timer.elapsed).ceil()),
+            None => String::from("Paused"),
+        };
+    }
+}
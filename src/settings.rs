@@ -0,0 +1,150 @@
+//! Player preferences picked from the menu and kept between sessions
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::PathBuf;
+
+use bevy::prelude::KeyCode;
+
+use crate::comp::input::{self, KeyAction, KeyBinds};
+use crate::config::MAX_PLAYERS;
+use crate::persist;
+
+/// What a player steers with
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InputDevice {
+    /// The player's own key set
+    Keyboard,
+    /// The gamepad with this index, in the order they were connected
+    Gamepad(usize),
+}
+impl InputDevice {
+    /// The next device along: the keyboard, then each gamepad slot, wrapping around
+    pub fn cycle(self, forward: bool) -> Self {
+        // keyboard is 0, gamepads are 1..=MAX_PLAYERS
+        let count = MAX_PLAYERS + 1;
+        let i = match self {
+            InputDevice::Keyboard => 0,
+            InputDevice::Gamepad(id) => id.min(MAX_PLAYERS - 1) + 1,
+        };
+        match if forward { (i + 1) % count } else { (i + count - 1) % count } {
+            0 => InputDevice::Keyboard,
+            i => InputDevice::Gamepad(i - 1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Indexed by player
+    pub devices: Vec<InputDevice>,
+    /// Each player's keys for when their device is the keyboard, indexed by player
+    pub keys: Vec<KeyBinds>,
+    /// How far a gamepad's left stick has to lean, from 0 to 1, before it counts as a turn
+    pub stick_dead_zone: f32,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            devices: vec![InputDevice::Keyboard; MAX_PLAYERS],
+            keys: (0..MAX_PLAYERS).map(KeyBinds::for_player).collect(),
+            stick_dead_zone: 0.5,
+            path: None,
+        }
+    }
+}
+
+impl Settings {
+    /// `<user config dir>/bevy_snake/settings.ron`
+    pub fn default_path() -> Option<PathBuf> {
+        persist::user_file(dirs::config_dir(), "settings.ron")
+    }
+
+    /// Reads the settings from [Settings::default_path], falling back to the defaults if they don't exist yet
+    pub fn load_or_default() -> Self {
+        let path = Settings::default_path();
+        let mut settings: Settings = persist::load_or_default(path.as_deref(), "settings");
+        if let Some(conflict) = settings.find_conflict() {
+            eprintln!("Resetting keys, {}", conflict);
+            settings.keys = Settings::default().keys;
+        }
+        settings.path = path;
+        settings
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        persist::save(self.path.as_deref(), self)
+    }
+
+    pub fn device(&self, player: usize) -> InputDevice {
+        self.devices.get(player).copied().unwrap_or(InputDevice::Keyboard)
+    }
+
+    pub fn keys(&self, player: usize) -> KeyBinds {
+        self.keys.get(player).copied().unwrap_or_else(|| KeyBinds::for_player(player))
+    }
+
+    /// Binds `key` to `action` for `player`.
+    ///
+    /// Refuses keys that can't be saved, Escape (the menu needs it), and keys already bound to
+    /// anything else by any player, explaining why in the error.
+    pub fn rebind(&mut self, player: usize, action: KeyAction, key: KeyCode) -> Result<(), String> {
+        if key == KeyCode::Escape {
+            return Err(String::from("Escape is kept for the menu"));
+        }
+        if !input::is_bindable(key) {
+            return Err(format!("{:?} can't be bound", key));
+        }
+        if let Some((other_player, other_action)) = self.bound_to(key) {
+            if (other_player, other_action) != (player, action) {
+                return Err(format!(
+                    "{:?} is already P{} {}",
+                    key,
+                    other_player + 1,
+                    other_action.name()
+                ));
+            }
+        }
+
+        while self.keys.len() <= player {
+            self.keys.push(KeyBinds::for_player(self.keys.len()));
+        }
+        self.keys[player].set(action, key);
+        Ok(())
+    }
+
+    /// The first player and action `key` is bound to
+    fn bound_to(&self, key: KeyCode) -> Option<(usize, KeyAction)> {
+        (0..MAX_PLAYERS.max(self.keys.len())).find_map(|player| {
+            let keys = self.keys(player);
+            KeyAction::ALL
+                .iter()
+                .find(|action| keys.get(**action) == key)
+                .map(|action| (player, *action))
+        })
+    }
+
+    /// Describes the first key bound twice, which can only come from a hand-edited file
+    fn find_conflict(&self) -> Option<String> {
+        for player in 0..MAX_PLAYERS.max(self.keys.len()) {
+            let keys = self.keys(player);
+            for action in KeyAction::ALL.iter() {
+                let key = keys.get(*action);
+                if self.bound_to(key) != Some((player, *action)) {
+                    return Some(format!("{:?} is bound more than once", key));
+                }
+            }
+        }
+        None
+    }
+
+    pub fn set_device(&mut self, player: usize, device: InputDevice) {
+        if self.devices.len() <= player {
+            self.devices.resize(player + 1, InputDevice::Keyboard);
+        }
+        self.devices[player] = device;
+    }
+}
@@ -0,0 +1,149 @@
+use super::GridPosition;
+use crate::comp::snake::SnakeDirection;
+
+/// The playable cells of the board, `min..=max` on both axes.
+///
+/// Wall placement, free-cell tracking and collision all go through this so they can't disagree about the edges.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Arena {
+    pub min: GridPosition,
+    pub max: GridPosition,
+    /// Stepping off an edge comes back in on the opposite one, instead of hitting a wall
+    pub wraps: bool,
+}
+
+impl Arena {
+    /// A square arena spanning `-grid_size..=grid_size` on both axes
+    pub fn square(grid_size: i32) -> Self {
+        Arena {
+            min: GridPosition::new(-grid_size, -grid_size),
+            max: GridPosition::new(grid_size, grid_size),
+            wraps: false,
+        }
+    }
+
+    /// The cell one step from `pos` in `direction`, brought back onto the board if the arena wraps.
+    ///
+    /// Outside a wrapping arena the result may lie off the board, which is a crash.
+    pub fn step(&self, pos: GridPosition, direction: SnakeDirection) -> GridPosition {
+        let next = pos.step(direction);
+        if !self.wraps {
+            return next;
+        }
+        GridPosition::new(
+            self.min.x + (next.x - self.min.x).rem_euclid(self.width()),
+            self.min.y + (next.y - self.min.y).rem_euclid(self.height()),
+        )
+    }
+
+    pub fn contains(&self, pos: GridPosition) -> bool {
+        pos.x >= self.min.x && pos.x <= self.max.x && pos.y >= self.min.y && pos.y <= self.max.y
+    }
+
+    pub fn width(&self) -> i32 {
+        self.max.x - self.min.x + 1
+    }
+
+    pub fn height(&self) -> i32 {
+        self.max.y - self.min.y + 1
+    }
+
+    /// Every playable cell, column by column
+    pub fn cells(&self) -> impl Iterator<Item = GridPosition> {
+        let Arena { min, max, .. } = *self;
+        (min.x..=max.x).flat_map(move |x| (min.y..=max.y).map(move |y| GridPosition::new(x, y)))
+    }
+
+    /// The four walls around a walled arena, one cell thick: left, right, bottom, top.
+    ///
+    /// The side walls run past the top and bottom rows so the corners are closed too.
+    pub fn wall_spans(&self) -> [WallSpan; 4] {
+        let center_x = (self.min.x + self.max.x) as f32 / 2.0;
+        let center_y = (self.min.y + self.max.y) as f32 / 2.0;
+        let width = (self.width() + 2) as f32;
+        let height = (self.height() + 2) as f32;
+        [
+            WallSpan {
+                center: ((self.min.x - 1) as f32, center_y),
+                size: (1.0, height),
+            },
+            WallSpan {
+                center: ((self.max.x + 1) as f32, center_y),
+                size: (1.0, height),
+            },
+            WallSpan {
+                center: (center_x, (self.min.y - 1) as f32),
+                size: (width, 1.0),
+            },
+            WallSpan {
+                center: (center_x, (self.max.y + 1) as f32),
+                size: (width, 1.0),
+            },
+        ]
+    }
+}
+
+/// A rectangle of wall, in cells, centered on `center`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct WallSpan {
+    pub center: (f32, f32),
+    pub size: (f32, f32),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Whether the cell at `pos` lies under `wall`
+    fn covers(wall: &WallSpan, pos: GridPosition) -> bool {
+        (pos.x as f32 - wall.center.0).abs() < wall.size.0 / 2.0 && (pos.y as f32 - wall.center.1).abs() < wall.size.1 / 2.0
+    }
+
+    #[test]
+    fn cells_cover_min_to_max_inclusive() {
+        let arena = Arena {
+            min: GridPosition::new(-2, -1),
+            max: GridPosition::new(3, 4),
+            wraps: false,
+        };
+        let cells: Vec<GridPosition> = arena.cells().collect();
+        assert_eq!(cells.len(), (arena.width() * arena.height()) as usize);
+        for x in -2..=3 {
+            for y in -1..=4 {
+                assert!(cells.contains(&GridPosition::new(x, y)), "missing ({}, {})", x, y);
+            }
+        }
+        assert!(cells.contains(&arena.max));
+        assert!(cells.contains(&GridPosition::new(arena.max.x, arena.min.y)));
+        assert!(cells.contains(&GridPosition::new(arena.min.x, arena.max.y)));
+    }
+
+    #[test]
+    fn walls_cover_exactly_the_ring_around_the_cells() {
+        let arena = Arena {
+            min: GridPosition::new(-2, -1),
+            max: GridPosition::new(3, 4),
+            wraps: false,
+        };
+        let walls = arena.wall_spans();
+        for x in arena.min.x - 2..=arena.max.x + 2 {
+            for y in arena.min.y - 2..=arena.max.y + 2 {
+                let pos = GridPosition::new(x, y);
+                let covering = walls.iter().filter(|wall| covers(wall, pos)).count();
+                let outside_x = x == arena.min.x - 1 || x == arena.max.x + 1;
+                let outside_y = y == arena.min.y - 1 || y == arena.max.y + 1;
+                let in_ring = !arena.contains(pos)
+                    && (arena.min.x - 1..=arena.max.x + 1).contains(&x)
+                    && (arena.min.y - 1..=arena.max.y + 1).contains(&y);
+                let corner = outside_x && outside_y;
+                let expected = match (in_ring, corner) {
+                    (false, _) => 0,
+                    // the side walls and the top or bottom wall both reach the corners
+                    (true, true) => 2,
+                    (true, false) => 1,
+                };
+                assert_eq!(covering, expected, "({}, {})", x, y);
+            }
+        }
+    }
+}
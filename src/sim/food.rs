@@ -0,0 +1,158 @@
+use serde::{Deserialize, Serialize};
+
+use super::{GameRng, GridPosition};
+
+/// Seconds a speed-up or slow-down lasts after it's eaten
+pub const SPEED_EFFECT_SECONDS: f32 = 5.0;
+
+/// Segments a [FoodKind::Shrink] takes off, never leaving the snake shorter than [START_LENGTH](super::START_LENGTH)
+pub const SHRINK_SEGMENTS: usize = 2;
+
+/// What a piece of food does to the snake that eats it
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FoodKind {
+    Normal,
+    /// Worth extra points
+    Golden,
+    /// Takes segments off instead of adding one
+    Shrink,
+    /// Makes the snake step faster for [SPEED_EFFECT_SECONDS]
+    SpeedUp,
+    /// Makes the snake step slower for [SPEED_EFFECT_SECONDS]
+    SlowDown,
+    /// Adds several segments at once
+    MultiGrow,
+}
+
+impl FoodKind {
+    pub const ALL: [FoodKind; 6] = [
+        FoodKind::Normal,
+        FoodKind::Golden,
+        FoodKind::Shrink,
+        FoodKind::SpeedUp,
+        FoodKind::SlowDown,
+        FoodKind::MultiGrow,
+    ];
+
+    pub fn points(self) -> u32 {
+        match self {
+            FoodKind::Golden => 5,
+            FoodKind::MultiGrow => 2,
+            _ => 1,
+        }
+    }
+
+    /// Segments added, one per step starting with the step that eats it
+    pub fn growth(self) -> usize {
+        match self {
+            FoodKind::Shrink => 0,
+            FoodKind::MultiGrow => 3,
+            _ => 1,
+        }
+    }
+
+    /// What the snake's step interval is multiplied by while the effect lasts
+    pub fn speed_factor(self) -> Option<f32> {
+        match self {
+            FoodKind::SpeedUp => Some(0.6),
+            FoodKind::SlowDown => Some(1.6),
+            _ => None,
+        }
+    }
+
+    /// Bonus food leaves the board after [FoodRules::bonus_seconds] if nobody eats it
+    pub fn is_bonus(self) -> bool {
+        self != FoodKind::Normal
+    }
+}
+
+/// How much food is on the board, which kinds turn up and how long bonus food stays
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FoodRules {
+    /// Pieces of food on the board at once, while there's room for them
+    pub count: usize,
+    /// Relative chance of each kind turning up
+    pub normal: u32,
+    pub golden: u32,
+    pub shrink: u32,
+    pub speed_up: u32,
+    pub slow_down: u32,
+    pub multi_grow: u32,
+    /// Seconds before uneaten bonus food moves to another cell, `None` to leave it until it's eaten
+    pub bonus_seconds: Option<f32>,
+}
+
+impl Default for FoodRules {
+    fn default() -> Self {
+        FoodRules {
+            count: 1,
+            normal: 12,
+            golden: 2,
+            shrink: 1,
+            speed_up: 1,
+            slow_down: 1,
+            multi_grow: 1,
+            bonus_seconds: Some(6.0),
+        }
+    }
+}
+
+impl FoodRules {
+    /// Only ever one piece of normal food, the rules before food kinds were added
+    pub fn classic() -> Self {
+        FoodRules {
+            count: 1,
+            normal: 1,
+            golden: 0,
+            shrink: 0,
+            speed_up: 0,
+            slow_down: 0,
+            multi_grow: 0,
+            bonus_seconds: None,
+        }
+    }
+
+    pub fn weight(&self, kind: FoodKind) -> u32 {
+        match kind {
+            FoodKind::Normal => self.normal,
+            FoodKind::Golden => self.golden,
+            FoodKind::Shrink => self.shrink,
+            FoodKind::SpeedUp => self.speed_up,
+            FoodKind::SlowDown => self.slow_down,
+            FoodKind::MultiGrow => self.multi_grow,
+        }
+    }
+
+    /// A random kind, by weight.
+    ///
+    /// Nothing is drawn from `rng` when only one kind can turn up, so the classic rules place food
+    /// exactly as they did before food kinds existed.
+    pub fn pick(&self, rng: &mut GameRng) -> FoodKind {
+        let possible: Vec<FoodKind> = FoodKind::ALL.iter().copied().filter(|kind| self.weight(*kind) > 0).collect();
+        match possible.as_slice() {
+            [] => return FoodKind::Normal,
+            [kind] => return *kind,
+            _ => {}
+        }
+
+        let total: u32 = possible.iter().map(|kind| self.weight(*kind)).sum();
+        let mut roll = rng.gen_range(0, total as usize) as u32;
+        for kind in possible {
+            if roll < self.weight(kind) {
+                return kind;
+            }
+            roll -= self.weight(kind);
+        }
+        unreachable!()
+    }
+}
+
+/// A piece of food on the board
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FoodItem {
+    pub pos: GridPosition,
+    pub kind: FoodKind,
+    /// [RunClock](crate::plugins::game_state::res::RunClock) time it moves elsewhere if still uneaten
+    pub expires: Option<f32>,
+}
@@ -0,0 +1,171 @@
+use super::{Arena, GameRng, GridPosition};
+
+/// What fills a cell of the [Arena]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Occupant {
+    /// Part of the given player's snake
+    Snake(usize),
+    Food,
+    /// An obstacle from the [Level](super::Level)
+    Wall,
+}
+
+/// A dense map from every cell of the [Arena] to what's in it.
+///
+/// The free cells are also kept in a flat list, with each free cell knowing its place in it, so marking a
+/// cell and picking a random free cell are both constant time however large the board is.
+pub struct Occupancy {
+    arena: Arena,
+    cells: Vec<Option<Occupant>>,
+    /// Every free cell, in no particular order. The order only depends on the sequence of [Occupancy::set]
+    /// calls, so random picks stay reproducible for a given seed.
+    free: Vec<GridPosition>,
+    /// Where each free cell sits in `free`, `None` for occupied cells
+    free_index: Vec<Option<usize>>,
+}
+
+impl Occupancy {
+    /// Every cell of `arena` starts out free
+    pub fn new(arena: Arena) -> Self {
+        let free: Vec<GridPosition> = arena.cells().collect();
+        Occupancy {
+            arena,
+            cells: vec![None; free.len()],
+            free_index: (0..free.len()).map(Some).collect(),
+            free,
+        }
+    }
+
+    /// Cells are stored column by column, the same order as [Arena::cells]
+    fn index(&self, pos: GridPosition) -> usize {
+        debug_assert!(self.arena.contains(pos));
+        ((pos.x - self.arena.min.x) * self.arena.height() + (pos.y - self.arena.min.y)) as usize
+    }
+
+    /// What's in the cell at `pos`, `None` if it's free or outside the arena
+    pub fn get(&self, pos: GridPosition) -> Option<Occupant> {
+        if !self.arena.contains(pos) {
+            return None;
+        }
+        self.cells[self.index(pos)]
+    }
+
+    pub fn is_free(&self, pos: GridPosition) -> bool {
+        self.arena.contains(pos) && self.cells[self.index(pos)].is_none()
+    }
+
+    /// Puts `occupant` in the cell at `pos`, or frees it with `None`
+    pub fn set(&mut self, pos: GridPosition, occupant: Option<Occupant>) {
+        let index = self.index(pos);
+        let was_free = self.cells[index].is_none();
+        self.cells[index] = occupant;
+
+        match (was_free, occupant.is_none()) {
+            (true, false) => {
+                // swap the last free cell into this one's slot
+                let slot = self.free_index[index].take().unwrap();
+                self.free.swap_remove(slot);
+                if let Some(moved) = self.free.get(slot).copied() {
+                    let moved_index = self.index(moved);
+                    self.free_index[moved_index] = Some(slot);
+                }
+            }
+            (false, true) => {
+                self.free_index[index] = Some(self.free.len());
+                self.free.push(pos);
+            }
+            _ => {}
+        }
+    }
+
+    /// A uniformly random free cell, `None` if the board is full
+    pub fn random_free(&self, rng: &mut GameRng) -> Option<GridPosition> {
+        if self.free.is_empty() {
+            return None;
+        }
+        Some(self.free[rng.gen_range(0, self.free.len())])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    /// Every free cell is listed in `free` exactly once, at the slot `free_index` says
+    fn assert_free_list_consistent(grid: &Occupancy) {
+        let free_cells = grid.cells.iter().filter(|cell| cell.is_none()).count();
+        assert_eq!(grid.free.len(), free_cells);
+        for pos in grid.arena.cells() {
+            let index = grid.index(pos);
+            match grid.free_index[index] {
+                Some(slot) => {
+                    assert!(grid.cells[index].is_none());
+                    assert_eq!(grid.free[slot], pos);
+                }
+                None => assert!(grid.cells[index].is_some()),
+            }
+        }
+    }
+
+    #[test]
+    fn set_keeps_free_list_in_step() {
+        let arena = Arena::square(3);
+        let mut grid = Occupancy::new(arena);
+        assert_free_list_consistent(&grid);
+
+        let mut rng = GameRng::new(Some(5));
+        let cells: Vec<GridPosition> = arena.cells().collect();
+        for _ in 0..5_000 {
+            let pos = cells[rng.gen_range(0, cells.len())];
+            let occupant = match rng.gen_range(0, 3) {
+                0 => None,
+                1 => Some(Occupant::Food),
+                _ => Some(Occupant::Snake(0)),
+            };
+            grid.set(pos, occupant);
+            assert_eq!(grid.get(pos), occupant);
+            assert_free_list_consistent(&grid);
+        }
+    }
+
+    #[test]
+    fn taking_the_last_free_slot_and_freeing_it_again() {
+        let arena = Arena::square(1);
+        let mut grid = Occupancy::new(arena);
+        let last = *grid.free.last().unwrap();
+        grid.set(last, Some(Occupant::Wall));
+        assert_free_list_consistent(&grid);
+        // changing what's in an occupied cell leaves the free list alone
+        grid.set(last, Some(Occupant::Food));
+        assert_free_list_consistent(&grid);
+        grid.set(last, None);
+        assert_free_list_consistent(&grid);
+        assert_eq!(grid.free.len(), 9);
+    }
+
+    #[test]
+    fn random_free_reaches_every_free_cell() {
+        let arena = Arena::square(2);
+        let mut grid = Occupancy::new(arena);
+        let taken = [GridPosition::new(0, 0), arena.min, arena.max];
+        for pos in taken.iter() {
+            grid.set(*pos, Some(Occupant::Wall));
+        }
+
+        let mut rng = GameRng::new(Some(1));
+        let picked: HashSet<GridPosition> = (0..2_000).filter_map(|_| grid.random_free(&mut rng)).collect();
+        let free: HashSet<GridPosition> = arena.cells().filter(|pos| !taken.contains(pos)).collect();
+        assert_eq!(picked, free);
+    }
+
+    #[test]
+    fn random_free_is_none_once_full() {
+        let arena = Arena::square(1);
+        let mut grid = Occupancy::new(arena);
+        for pos in arena.cells() {
+            grid.set(pos, Some(Occupant::Food));
+        }
+        assert_eq!(grid.random_free(&mut GameRng::new(Some(1))), None);
+    }
+}
@@ -0,0 +1,192 @@
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use super::{Arena, GridPosition, START_LENGTH};
+use crate::comp::snake::SnakeDirection;
+
+/// The levels that ship with the game, in menu order
+const BUILTIN_LEVELS: [&str; 4] = [
+    include_str!("../../assets/levels/pillars.ron"),
+    include_str!("../../assets/levels/cross.ron"),
+    include_str!("../../assets/levels/corridors.ron"),
+    include_str!("../../assets/levels/rooms.ron"),
+];
+
+/// A level as written in its RON file.
+///
+/// The board is drawn as rows of characters, top row first:
+/// `.` is floor, `#` an obstacle, `*` the first food and `1` to `4` the head of that player's snake.
+/// Each snake's body trails behind its head, away from its entry in `directions` (up if missing).
+/// A `goal` puts the level in the campaign.
+#[derive(Debug, Clone, Deserialize)]
+struct LevelFile {
+    name: String,
+    map: Vec<String>,
+    #[serde(default)]
+    directions: Vec<SnakeDirection>,
+    #[serde(default)]
+    goal: Option<LevelGoal>,
+}
+
+/// What a snake has to do to complete a level in the campaign
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize)]
+pub enum LevelGoal {
+    /// Eat this many food
+    Eat(u32),
+    /// Grow to this many segments
+    Length(usize),
+    /// Stay alive for this many seconds
+    Survive(f32),
+}
+
+impl LevelGoal {
+    pub fn is_met(self, food: u32, length: usize, elapsed: f32) -> bool {
+        match self {
+            LevelGoal::Eat(target) => food >= target,
+            LevelGoal::Length(target) => length >= target,
+            LevelGoal::Survive(target) => elapsed >= target,
+        }
+    }
+}
+
+impl fmt::Display for LevelGoal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LevelGoal::Eat(target) => write!(f, "Eat {} food", target),
+            LevelGoal::Length(target) => write!(f, "Grow to {} long", target),
+            LevelGoal::Survive(target) => write!(f, "Survive {}s", target),
+        }
+    }
+}
+
+/// Where one player's snake starts
+#[derive(Debug, Clone)]
+pub struct Spawn {
+    /// Head first
+    pub body: Vec<GridPosition>,
+    pub direction: SnakeDirection,
+}
+
+/// A board layout: its size, obstacles, where the snakes start and where the first food goes
+#[derive(Debug, Clone)]
+pub struct Level {
+    pub name: String,
+    pub arena: Arena,
+    pub obstacles: Vec<GridPosition>,
+    /// In player order, at least one
+    pub spawns: Vec<Spawn>,
+    /// `None` to put the first food in the first free cell
+    pub food: Option<GridPosition>,
+    /// Levels without a goal can be played on their own but aren't part of the campaign
+    pub goal: Option<LevelGoal>,
+}
+
+impl Level {
+    pub fn load(path: &Path) -> Result<Level, Box<dyn Error>> {
+        Level::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Reads a level from its RON text, checking that the map is rectangular and every snake fits on the floor
+    pub fn parse(contents: &str) -> Result<Level, Box<dyn Error>> {
+        let file: LevelFile = ron::de::from_str(contents)?;
+
+        let height = file.map.len() as i32;
+        let width = file.map.first().map_or(0, |row| row.chars().count()) as i32;
+        if width == 0 {
+            return Err(format!("level {} has an empty map", file.name).into());
+        }
+        let min = GridPosition::new(-width / 2, -height / 2);
+        let arena = Arena {
+            min,
+            max: GridPosition::new(min.x + width - 1, min.y + height - 1),
+            wraps: false,
+        };
+
+        let mut obstacles = Vec::new();
+        let mut heads: Vec<Option<GridPosition>> = Vec::new();
+        let mut food = None;
+        for (row, line) in file.map.iter().enumerate() {
+            if line.chars().count() as i32 != width {
+                return Err(format!("row {} of level {} isn't {} cells wide", row + 1, file.name, width).into());
+            }
+            let y = arena.max.y - row as i32;
+            for (column, cell) in line.chars().enumerate() {
+                let pos = GridPosition::new(arena.min.x + column as i32, y);
+                match cell {
+                    '.' => {}
+                    '#' => obstacles.push(pos),
+                    '*' if food.is_some() => {
+                        return Err(format!("level {} has more than one food", file.name).into())
+                    }
+                    '*' => food = Some(pos),
+                    '1'..='4' => {
+                        let player = cell.to_digit(10).unwrap() as usize - 1;
+                        if heads.len() <= player {
+                            heads.resize(player + 1, None);
+                        }
+                        if heads[player].replace(pos).is_some() {
+                            return Err(format!("level {} has two snakes for player {}", file.name, cell).into());
+                        }
+                    }
+                    _ => return Err(format!("level {} has an unknown cell {:?}", file.name, cell).into()),
+                }
+            }
+        }
+
+        let mut taken: HashSet<GridPosition> = obstacles.iter().copied().chain(food).collect();
+        let mut spawns = Vec::new();
+        for (player, head) in heads.into_iter().enumerate() {
+            let head = head.ok_or_else(|| format!("level {} has no snake for player {}", file.name, player + 1))?;
+            let direction = file.directions.get(player).copied().unwrap_or(SnakeDirection::Up);
+            let behind = direction.opposite();
+
+            let mut body = vec![head];
+            while body.len() < START_LENGTH {
+                body.push(body.last().unwrap().step(behind));
+            }
+            if body.iter().any(|pos| !arena.contains(*pos) || !taken.insert(*pos)) {
+                return Err(format!("player {}'s snake doesn't fit on the floor of level {}", player + 1, file.name).into());
+            }
+            spawns.push(Spawn { body, direction });
+        }
+        if spawns.is_empty() {
+            return Err(format!("level {} has no snakes", file.name).into());
+        }
+
+        Ok(Level {
+            name: file.name,
+            arena,
+            obstacles,
+            spawns,
+            food,
+            goal: file.goal,
+        })
+    }
+}
+
+/// Every level that can be picked from the menu
+pub struct Levels(pub Vec<Level>);
+
+impl Levels {
+    pub fn builtin() -> Self {
+        Levels(
+            BUILTIN_LEVELS
+                .iter()
+                .map(|contents| Level::parse(contents).unwrap_or_else(|e| panic!("Invalid built-in level: {}", e)))
+                .collect(),
+        )
+    }
+
+    pub fn find(&self, name: &str) -> Option<&Level> {
+        self.0.iter().find(|level| level.name == name)
+    }
+
+    /// The levels with a goal, in the order the campaign plays them
+    pub fn campaign(&self) -> Vec<&Level> {
+        self.0.iter().filter(|level| level.goal.is_some()).collect()
+    }
+}
@@ -0,0 +1,601 @@
+//! Headless snake rules.
+//!
+//! Nothing in here knows about Bevy. The [SnakeWorld] owns the grid, the snakes and the food,
+//! and the Bevy systems only mirror what [SnakeWorld::step] reports back into sprites.
+use std::collections::VecDeque;
+
+use crate::comp::snake::SnakeDirection;
+
+mod arena;
+mod food;
+mod grid;
+mod level;
+mod replay;
+mod rng;
+mod speed;
+pub use arena::Arena;
+pub use food::{FoodItem, FoodKind, FoodRules, SHRINK_SEGMENTS, SPEED_EFFECT_SECONDS};
+pub use grid::{Occupancy, Occupant};
+pub use level::{Level, LevelGoal, Levels};
+pub use replay::Replay;
+pub use rng::GameRng;
+pub use speed::{SpeedCurve, SpeedUp, MIN_STEP_SECONDS};
+
+/// How many segments every snake starts with
+pub const START_LENGTH: usize = 3;
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone, Debug)]
+pub struct GridPosition {
+    pub x: i32,
+    pub y: i32,
+}
+impl GridPosition {
+    pub fn new(x: i32, y: i32) -> GridPosition {
+        GridPosition { x, y }
+    }
+
+    /// The neighbouring cell in `direction`
+    pub fn step(self, direction: SnakeDirection) -> GridPosition {
+        match direction {
+            SnakeDirection::Up => GridPosition::new(self.x, self.y + 1),
+            SnakeDirection::Down => GridPosition::new(self.x, self.y - 1),
+            SnakeDirection::Left => GridPosition::new(self.x - 1, self.y),
+            SnakeDirection::Right => GridPosition::new(self.x + 1, self.y),
+        }
+    }
+}
+
+/// What happened to one snake during a single [SnakeWorld::step]
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum StepOutcome {
+    /// The head moved to `head` and the tail left `vacated`, or stayed put while the snake is growing
+    Moved {
+        head: GridPosition,
+        vacated: Option<GridPosition>,
+    },
+    /// The head moved onto food of `kind` at `head`
+    Ate { head: GridPosition, kind: FoodKind },
+    /// The head moved onto food of `kind` at `head`, which left no food on the board and nowhere to put more
+    BoardFilled { head: GridPosition, kind: FoodKind },
+    /// The head would have moved into the snake's own body
+    HitSelf,
+    /// The head would have moved into another snake, or into the cell another head moved into
+    HitOtherSnake,
+    /// The head would have left an [Arena] that doesn't wrap, or hit one of the [Level]'s obstacles
+    HitWall,
+    /// The snake sat this step out, waiting for its own next step
+    Held,
+    /// The snake was already out before this step
+    Out,
+}
+
+/// One player's snake
+pub struct SimSnake {
+    /// Head at the front, tail at the back
+    pub body: VecDeque<GridPosition>,
+    pub direction: SnakeDirection,
+    /// Cleared once the snake crashes or leaves the game, after which its body no longer blocks anyone
+    pub alive: bool,
+    /// Segments still to be added, one per step, by keeping the tail where it is
+    pub growing: usize,
+    /// Pieces of food eaten this run, whatever their kind
+    pub eaten: u32,
+}
+
+impl SimSnake {
+    pub fn head(&self) -> GridPosition {
+        *self.body.front().unwrap()
+    }
+
+    pub fn tail(&self) -> GridPosition {
+        *self.body.back().unwrap()
+    }
+}
+
+pub struct SnakeWorld {
+    pub arena: Arena,
+    /// Indexed by player
+    pub snakes: Vec<SimSnake>,
+    /// Every piece of food on the board, empty once the board has been filled
+    pub food: Vec<FoodItem>,
+    /// How much food there is and which kinds turn up after the first
+    pub food_rules: FoodRules,
+    /// What's in every cell, kept in step with `snakes` and `food`
+    pub grid: Occupancy,
+    /// Number of steps taken since the last [SnakeWorld::reset]
+    pub tick: u64,
+    /// What a snake has to do to complete the level, `None` outside the campaign
+    pub goal: Option<LevelGoal>,
+    /// The player who met the [SnakeWorld::goal] first and the run time of that step
+    pub completed: Option<(usize, f32)>,
+}
+
+impl SnakeWorld {
+    pub fn new(arena: Arena, players: usize) -> Self {
+        let mut world = SnakeWorld {
+            arena,
+            snakes: Vec::new(),
+            food: Vec::new(),
+            food_rules: FoodRules::default(),
+            grid: Occupancy::new(arena),
+            tick: 0,
+            goal: None,
+            completed: None,
+        };
+        world.reset(players);
+        world
+    }
+
+    /// The most snakes that fit side by side on `arena` with a free column between them
+    pub fn max_players(arena: Arena) -> usize {
+        ((arena.width() + 1) / 2) as usize
+    }
+
+    /// How many snakes a run asked for `players` gets: at least one, and no more than fit on the open
+    /// `arena` or than `level` has spawns for
+    pub fn player_count(arena: Arena, level: Option<&Level>, players: usize) -> usize {
+        let most = level.map_or_else(|| SnakeWorld::max_players(arena), |level| level.spawns.len());
+        players.max(1).min(most)
+    }
+
+    /// Puts `players` snakes and the first food back in their starting cells on the open board.
+    ///
+    /// The snakes start spread evenly across the arena heading up, dropped down if the arena is too short
+    /// for the usual row, so a lone snake starts in the middle column. The food falls back to the first
+    /// free cell if its usual spot isn't available. [SnakeWorld::top_up_food] adds the rest of the food.
+    pub fn reset(&mut self, players: usize) {
+        let players = SnakeWorld::player_count(self.arena, None, players);
+        let width = self.arena.width();
+        let y = 2.min(self.arena.max.y);
+
+        let snakes = (0..players as i32)
+            .map(|player| {
+                let x = self.arena.min.x + width * (2 * player + 1) / (2 * players as i32);
+                SimSnake {
+                    body: (0..START_LENGTH as i32).map(|i| GridPosition::new(x, y - i)).collect(),
+                    direction: SnakeDirection::Up,
+                    alive: true,
+                    growing: 0,
+                    eaten: 0,
+                }
+            })
+            .collect();
+        self.start(snakes, &[], Some(GridPosition::new(-3, 2)));
+    }
+
+    /// Lays out `level` and puts up to `players` snakes on its spawns.
+    ///
+    /// The arena takes the level's size but keeps wrapping if it already did.
+    pub fn load_level(&mut self, level: &Level, players: usize) {
+        self.arena = Arena {
+            wraps: self.arena.wraps,
+            ..level.arena
+        };
+        let snakes = level
+            .spawns
+            .iter()
+            .take(SnakeWorld::player_count(self.arena, Some(level), players))
+            .map(|spawn| SimSnake {
+                body: spawn.body.iter().copied().collect(),
+                direction: spawn.direction,
+                alive: true,
+                growing: 0,
+                eaten: 0,
+            })
+            .collect();
+        self.start(snakes, &level.obstacles, level.food);
+    }
+
+    /// Fills a fresh grid with `obstacles` and `snakes`, then puts one normal food at `food`,
+    /// or in the first free cell if that isn't available
+    fn start(&mut self, snakes: Vec<SimSnake>, obstacles: &[GridPosition], food: Option<GridPosition>) {
+        self.snakes = snakes;
+        self.tick = 0;
+        self.completed = None;
+
+        self.grid = Occupancy::new(self.arena);
+        for pos in obstacles {
+            self.grid.set(*pos, Some(Occupant::Wall));
+        }
+        for (player, snake) in self.snakes.iter().enumerate() {
+            for pos in snake.body.iter() {
+                self.grid.set(*pos, Some(Occupant::Snake(player)));
+            }
+        }
+
+        let grid = &self.grid;
+        let food = match food {
+            Some(food) if grid.is_free(food) => Some(food),
+            _ => self.arena.cells().find(|pos| grid.is_free(*pos)),
+        };
+        self.food = food
+            .map(|pos| FoodItem {
+                pos,
+                kind: FoodKind::Normal,
+                expires: None,
+            })
+            .into_iter()
+            .collect();
+        if let Some(food) = food {
+            self.grid.set(food, Some(Occupant::Food));
+        }
+    }
+
+    /// Places new food until there are [FoodRules::count] pieces on the board or no free cell is left
+    pub fn top_up_food(&mut self, now: f32, rng: &mut GameRng) {
+        while self.food.len() < self.food_rules.count {
+            match self.place_food(now, rng) {
+                Some(food) => self.food.push(food),
+                None => break,
+            }
+        }
+    }
+
+    /// Puts a new piece of food in a random free cell, `None` if the board is full
+    fn place_food(&mut self, now: f32, rng: &mut GameRng) -> Option<FoodItem> {
+        let pos = self.grid.random_free(rng)?;
+        let kind = self.food_rules.pick(rng);
+        let expires = match self.food_rules.bonus_seconds {
+            Some(seconds) if kind.is_bonus() => Some(now + seconds),
+            _ => None,
+        };
+        self.grid.set(pos, Some(Occupant::Food));
+        Some(FoodItem { pos, kind, expires })
+    }
+
+    pub fn alive_count(&self) -> usize {
+        self.snakes.iter().filter(|snake| snake.alive).count()
+    }
+
+    /// Whether the run is over: the level is complete, the board is full, nobody is left, or only one snake
+    /// is left in a multiplayer game
+    pub fn is_over(&self) -> bool {
+        let alive = self.alive_count();
+        self.completed.is_some() || self.food.is_empty() || alive == 0 || (self.snakes.len() > 1 && alive == 1)
+    }
+
+    /// Takes `player`'s snake out of the game, freeing the cells it took up
+    pub fn eliminate(&mut self, player: usize) {
+        let snake = &mut self.snakes[player];
+        if !snake.alive {
+            return;
+        }
+        snake.alive = false;
+        for pos in snake.body.iter() {
+            self.grid.set(*pos, None);
+        }
+    }
+
+    /// Advances the snakes still in the game by one cell, all at once.
+    ///
+    /// `inputs` holds one entry per player: the direction to move in, ignored if it would reverse that
+    /// snake onto itself, or `None` to hold the snake where it is for this step.
+    /// `now` is the run time of the step, which decides whether bonus food has expired and whether a
+    /// [LevelGoal::Survive] goal is met. A snake meeting the [SnakeWorld::goal] on this step completes the
+    /// level even if the step also ends the run some other way.
+    /// Returns one [StepOutcome] per player.
+    pub fn step(&mut self, inputs: &[Option<SnakeDirection>], now: f32, rng: &mut GameRng) -> Vec<StepOutcome> {
+        self.tick += 1;
+
+        let grid = &mut self.grid;
+        self.food.retain(|food| {
+            let expired = food.expires.map_or(false, |expires| expires <= now);
+            if expired {
+                grid.set(food.pos, None);
+            }
+            !expired
+        });
+        self.top_up_food(now, rng);
+
+        let mut next = Vec::with_capacity(self.snakes.len());
+        for (snake, input) in self.snakes.iter_mut().zip(inputs) {
+            let input = match input {
+                Some(input) if snake.alive => *input,
+                _ => {
+                    next.push(None);
+                    continue;
+                }
+            };
+            if !input.is_opposite(snake.direction) {
+                snake.direction = input;
+            }
+            next.push(Some(self.arena.step(snake.head(), snake.direction)));
+        }
+
+        // The food each snake is about to eat, and the growth still to come once it has. A snake's tail
+        // moves out of the way this step unless it's held or growing.
+        let eating: Vec<Option<FoodItem>> = next
+            .iter()
+            .map(|cell| cell.and_then(|cell| self.food.iter().copied().find(|food| food.pos == cell)))
+            .collect();
+        let growing: Vec<usize> = self
+            .snakes
+            .iter()
+            .zip(eating.iter())
+            .map(|(snake, food)| snake.growing + food.map_or(0, |food| food.kind.growth()))
+            .collect();
+        let tail_stays = |player: usize| next[player].is_none() || growing[player] > 0;
+
+        let mut outcomes = Vec::with_capacity(self.snakes.len());
+        for (player, snake) in self.snakes.iter().enumerate() {
+            if !snake.alive {
+                outcomes.push(StepOutcome::Out);
+                continue;
+            }
+            let cell = match next[player] {
+                Some(cell) => cell,
+                None => {
+                    outcomes.push(StepOutcome::Held);
+                    continue;
+                }
+            };
+
+            let occupant = self.grid.get(cell);
+            let hit = match occupant {
+                Some(Occupant::Snake(other)) if cell == self.snakes[other].tail() && !tail_stays(other) => None,
+                Some(Occupant::Snake(other)) => Some(other),
+                _ => None,
+            };
+            let head_on = self
+                .snakes
+                .iter()
+                .enumerate()
+                .any(|(other, other_snake)| other != player && other_snake.alive && next[other] == Some(cell));
+
+            let outcome = if !self.arena.contains(cell) || occupant == Some(Occupant::Wall) {
+                StepOutcome::HitWall
+            } else if hit == Some(player) {
+                StepOutcome::HitSelf
+            } else if hit.is_some() || head_on {
+                StepOutcome::HitOtherSnake
+            } else {
+                // filled in below, once every crash is known
+                StepOutcome::Out
+            };
+            outcomes.push(outcome);
+        }
+
+        for (player, outcome) in outcomes.iter().enumerate() {
+            match outcome {
+                StepOutcome::HitWall | StepOutcome::HitSelf | StepOutcome::HitOtherSnake => {
+                    self.eliminate(player)
+                }
+                _ => {}
+            }
+        }
+
+        // tails first, so a head can follow another snake's tail into the cell it leaves
+        let mut vacated = vec![None; self.snakes.len()];
+        for (player, snake) in self.snakes.iter_mut().enumerate() {
+            if !snake.alive || next[player].is_none() {
+                continue;
+            }
+            if growing[player] > 0 {
+                snake.growing = growing[player] - 1;
+            } else {
+                let tail = snake.body.pop_back().unwrap();
+                self.grid.set(tail, None);
+                vacated[player] = Some(tail);
+            }
+        }
+        for (player, snake) in self.snakes.iter_mut().enumerate() {
+            let head = match next[player] {
+                Some(head) if snake.alive => head,
+                _ => continue,
+            };
+            snake.body.push_front(head);
+            self.grid.set(head, Some(Occupant::Snake(player)));
+            outcomes[player] = StepOutcome::Moved {
+                head,
+                vacated: vacated[player],
+            };
+        }
+
+        // two heads can't share a cell, so every piece of food has at most one eater
+        let mut eaters = Vec::new();
+        for (player, snake) in self.snakes.iter_mut().enumerate() {
+            let food = match eating[player] {
+                Some(food) if snake.alive => food,
+                _ => continue,
+            };
+            self.food.retain(|other| other.pos != food.pos);
+
+            snake.eaten += 1;
+            if food.kind == FoodKind::Shrink {
+                for _ in 0..SHRINK_SEGMENTS.min(snake.body.len().saturating_sub(START_LENGTH)) {
+                    let tail = snake.body.pop_back().unwrap();
+                    self.grid.set(tail, None);
+                }
+            }
+            eaters.push((player, food));
+        }
+
+        self.top_up_food(now, rng);
+        for (player, food) in eaters {
+            outcomes[player] = if self.food.is_empty() {
+                StepOutcome::BoardFilled { head: food.pos, kind: food.kind }
+            } else {
+                StepOutcome::Ate { head: food.pos, kind: food.kind }
+            };
+        }
+
+        if let (Some(goal), None) = (self.goal, self.completed) {
+            self.completed = self
+                .snakes
+                .iter()
+                .position(|snake| snake.alive && goal.is_met(snake.eaten, snake.body.len(), now))
+                .map(|player| (player, now));
+        }
+
+        outcomes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::time::{Duration, Instant};
+
+    /// The way from `from` to the neighbouring cell `to`
+    fn direction(from: GridPosition, to: GridPosition) -> SnakeDirection {
+        match (to.x - from.x, to.y - from.y) {
+            (1, 0) => SnakeDirection::Right,
+            (-1, 0) => SnakeDirection::Left,
+            (0, 1) => SnakeDirection::Up,
+            _ => SnakeDirection::Down,
+        }
+    }
+
+    #[test]
+    fn tiny_board_plays_to_completion() {
+        let arena = Arena {
+            min: GridPosition::new(0, 0),
+            max: GridPosition::new(1, 2),
+            wraps: false,
+        };
+        let mut world = SnakeWorld::new(arena, 1);
+        world.food_rules = FoodRules::classic();
+        let mut rng = GameRng::new(Some(9));
+
+        // a loop through every cell, so the snake can never run into itself
+        let cycle = [
+            GridPosition::new(1, 2),
+            GridPosition::new(0, 2),
+            GridPosition::new(0, 1),
+            GridPosition::new(0, 0),
+            GridPosition::new(1, 0),
+            GridPosition::new(1, 1),
+        ];
+        let mut outcome = StepOutcome::Held;
+        for step in 0..100 {
+            let head = world.snakes[0].head();
+            let at = cycle.iter().position(|pos| *pos == head).unwrap();
+            let next = cycle[(at + 1) % cycle.len()];
+            outcome = world.step(&[Some(direction(head, next))], step as f32, &mut rng)[0];
+            if world.is_over() {
+                break;
+            }
+        }
+
+        assert!(matches!(outcome, StepOutcome::BoardFilled { .. }), "{:?}", outcome);
+        assert!(world.is_over());
+        assert_eq!(world.snakes[0].body.len(), 6);
+        assert!(world.food.is_empty());
+    }
+
+    #[test]
+    fn food_on_the_open_board_reaches_every_free_cell() {
+        let arena = Arena::square(2);
+        let mut world = SnakeWorld::new(arena, 1);
+        world.food_rules = FoodRules::classic();
+        let mut rng = GameRng::new(Some(3));
+
+        let mut placed = HashSet::new();
+        for _ in 0..2_000 {
+            for food in world.food.drain(..) {
+                world.grid.set(food.pos, None);
+            }
+            world.top_up_food(0.0, &mut rng);
+            placed.extend(world.food.iter().map(|food| food.pos));
+        }
+        for food in world.food.drain(..) {
+            world.grid.set(food.pos, None);
+        }
+
+        // everything but the snake
+        let free: HashSet<GridPosition> = arena.cells().filter(|pos| world.grid.get(*pos).is_none()).collect();
+        assert!(placed.contains(&arena.max));
+        assert!(placed.contains(&arena.min));
+        assert_eq!(placed, free);
+    }
+
+    #[test]
+    fn survive_goal_is_met_on_the_step_that_reaches_it() {
+        let arena = Arena {
+            min: GridPosition::new(0, 0),
+            max: GridPosition::new(1, 2),
+            wraps: false,
+        };
+        let mut world = SnakeWorld::new(arena, 1);
+        world.food_rules = FoodRules::classic();
+        world.goal = Some(LevelGoal::Survive(1.5));
+        let mut rng = GameRng::new(Some(9));
+
+        let cycle = [
+            GridPosition::new(1, 2),
+            GridPosition::new(0, 2),
+            GridPosition::new(0, 1),
+            GridPosition::new(0, 0),
+            GridPosition::new(1, 0),
+            GridPosition::new(1, 1),
+        ];
+        for now in &[0.0, 1.0, 2.0] {
+            assert_eq!(world.completed, None);
+            assert!(!world.is_over());
+            let head = world.snakes[0].head();
+            let at = cycle.iter().position(|pos| *pos == head).unwrap();
+            let next = cycle[(at + 1) % cycle.len()];
+            world.step(&[Some(direction(head, next))], *now, &mut rng);
+        }
+
+        assert_eq!(world.completed, Some((0, 2.0)));
+        assert!(world.is_over());
+    }
+
+    /// A loop through every cell of an `n` by `n` board from `(0, 0)`, `n` even: along the bottom row,
+    /// then snaking up and down the columns back to the start
+    fn board_cycle(n: i32) -> Vec<GridPosition> {
+        let mut cycle: Vec<GridPosition> = (0..n).map(|x| GridPosition::new(x, 0)).collect();
+        for x in (0..n).rev() {
+            let ys: Vec<i32> = if (n - 1 - x) % 2 == 0 { (1..n).collect() } else { (1..n).rev().collect() };
+            cycle.extend(ys.into_iter().map(|y| GridPosition::new(x, y)));
+        }
+        cycle
+    }
+
+    /// Average time of one step of a single snake `length` segments long on a 200x200 board
+    fn time_step(length: usize) -> Duration {
+        let n = 200;
+        let cycle = board_cycle(n);
+        let arena = Arena {
+            min: GridPosition::new(0, 0),
+            max: GridPosition::new(n - 1, n - 1),
+            wraps: false,
+        };
+        let mut world = SnakeWorld::new(arena, 1);
+        world.food_rules = FoodRules::classic();
+        let snake = SimSnake {
+            body: cycle[..length].iter().rev().copied().collect(),
+            direction: direction(cycle[length - 2], cycle[length - 1]),
+            alive: true,
+            growing: 0,
+            eaten: 0,
+        };
+        world.start(vec![snake], &[], None);
+
+        let mut rng = GameRng::new(Some(1));
+        let ticks = 5_000;
+        let mut at = length - 1;
+        let started = Instant::now();
+        for tick in 0..ticks {
+            let next = (at + 1) % cycle.len();
+            let outcome = world.step(&[Some(direction(cycle[at], cycle[next]))], tick as f32, &mut rng)[0];
+            assert!(matches!(outcome, StepOutcome::Moved { .. } | StepOutcome::Ate { .. }), "{:?}", outcome);
+            at = next;
+        }
+        started.elapsed() / ticks
+    }
+
+    /// `cargo test --release -- --ignored --nocapture` to see the timings
+    #[test]
+    #[ignore]
+    fn step_time_does_not_grow_with_length() {
+        let short = time_step(3);
+        for &length in &[1_000, 10_000, 30_000] {
+            let long = time_step(length);
+            println!("length {:>6}: {:?} per step (length 3: {:?})", length, long, short);
+            assert!(long < short * 20 + Duration::from_micros(20), "length {} took {:?}", length, long);
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use super::{FoodRules, SpeedCurve};
+use crate::comp::snake::SnakeDirection;
+use crate::config::{GameConfig, GameMode};
+
+/// Bumped whenever a change to the rules would make older replays play out differently
+pub const REPLAY_VERSION: u32 = 3;
+
+/// A player's direction change and the movement tick it took effect on
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct ReplayInput {
+    pub tick: u64,
+    pub player: usize,
+    pub direction: SnakeDirection,
+}
+
+/// A player giving up, between the movement tick it's recorded on and the next one
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct ReplayForfeit {
+    pub tick: u64,
+    pub player: usize,
+}
+
+/// Everything needed to play a run back exactly: the seed, the board and the inputs.
+///
+/// Only replays of the current [REPLAY_VERSION] are read, so every field is required.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    /// The [REPLAY_VERSION] it was recorded with
+    pub version: u32,
+    pub seed: u64,
+    pub grid_size: i32,
+    pub wrap_around: bool,
+    /// The [Level](super::Level) played, `None` for the open board
+    pub level: Option<String>,
+    pub players: usize,
+    pub food: FoodRules,
+    /// Step times, and so bonus food expiry, follow these, so they have to match the recording
+    pub tick_seconds: f32,
+    pub speed: SpeedCurve,
+    pub mode: GameMode,
+    pub time_limit: Option<f32>,
+    pub inputs: Vec<ReplayInput>,
+    pub forfeits: Vec<ReplayForfeit>,
+}
+
+/// Just enough of a [Replay] to tell whether the rest can be read
+#[derive(Deserialize)]
+struct ReplayHeader {
+    /// 0 for replays from before versions were kept
+    #[serde(default)]
+    version: u32,
+}
+
+impl Replay {
+    /// An empty recording of a game with `players` snakes, played by the rules in `config`
+    pub fn new(seed: u64, config: &GameConfig, players: usize) -> Self {
+        Replay {
+            version: REPLAY_VERSION,
+            seed,
+            grid_size: config.grid_size,
+            wrap_around: config.wrap_around,
+            level: config.level.clone(),
+            players,
+            food: config.food,
+            tick_seconds: config.tick_seconds,
+            speed: config.speed,
+            mode: config.mode,
+            time_limit: config.time_limit,
+            inputs: Vec::new(),
+            forfeits: Vec::new(),
+        }
+    }
+
+    /// Sets `config` up to play the game back by the rules it was recorded with
+    pub fn apply_to(&self, config: &mut GameConfig) {
+        config.grid_size = self.grid_size;
+        config.wrap_around = self.wrap_around;
+        config.level = self.level.clone();
+        config.food = self.food;
+        config.tick_seconds = self.tick_seconds;
+        config.speed = self.speed;
+        config.mode = self.mode;
+        config.time_limit = self.time_limit;
+        config.players = self.players;
+        config.seed = Some(self.seed);
+    }
+
+    pub fn record(&mut self, tick: u64, player: usize, direction: SnakeDirection) {
+        self.inputs.push(ReplayInput { tick, player, direction });
+    }
+
+    pub fn record_forfeit(&mut self, tick: u64, player: usize) {
+        self.forfeits.push(ReplayForfeit { tick, player });
+    }
+
+    /// Whether `player` gave up after `tick`
+    pub fn forfeited_at(&self, tick: u64, player: usize) -> bool {
+        self.forfeits
+            .iter()
+            .any(|forfeit| forfeit.tick == tick && forfeit.player == player)
+    }
+
+    /// The direction change recorded for `player` on `tick`, if any
+    pub fn input_at(&self, tick: u64, player: usize) -> Option<SnakeDirection> {
+        self.inputs
+            .iter()
+            .find(|input| input.tick == tick && input.player == player)
+            .map(|input| input.direction)
+    }
+
+    /// Reads a replay, refusing one recorded under different rules since it would no longer play out the same
+    pub fn load(path: &Path) -> Result<Replay, Box<dyn Error>> {
+        let contents = fs::read_to_string(path)?;
+        let header: ReplayHeader = ron::de::from_str(&contents)?;
+        if header.version != REPLAY_VERSION {
+            return Err(format!(
+                "replay format version {} can't be played back by this version, which plays version {}",
+                header.version, REPLAY_VERSION
+            )
+            .into());
+        }
+        Ok(ron::de::from_str(&contents)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        fs::write(path, contents)?;
+        Ok(())
+    }
+}
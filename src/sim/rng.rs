@@ -0,0 +1,37 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// The only source of randomness for the game rules.
+///
+/// When `fixed_seed` is set every game replays the same food sequence, otherwise a fresh seed is
+/// rolled at the start of each game. Either way the seed in use is kept around so a run can be reproduced.
+pub struct GameRng {
+    pub fixed_seed: Option<u64>,
+    seed: u64,
+    rng: StdRng,
+}
+
+impl GameRng {
+    pub fn new(fixed_seed: Option<u64>) -> Self {
+        let seed = fixed_seed.unwrap_or_else(|| rand::thread_rng().gen());
+        GameRng {
+            fixed_seed,
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// Re-seeds the generator for a new game
+    pub fn start_game(&mut self) {
+        *self = GameRng::new(self.fixed_seed);
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Uniform value in `low..high`
+    pub fn gen_range(&mut self, low: usize, high: usize) -> usize {
+        self.rng.gen_range(low, high)
+    }
+}
@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+
+/// No step is ever shorter than this, whatever the config or food effects say, so the steps
+/// due in one frame always run out
+pub const MIN_STEP_SECONDS: f32 = 0.02;
+
+/// What shortens a snake's step interval over a run
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpeedUp {
+    /// Every step takes the base interval
+    Never,
+    PerFood,
+    /// Per segment grown past [START_LENGTH](super::START_LENGTH)
+    PerLength,
+    /// Per second the run has lasted
+    PerSecond,
+}
+
+/// How a snake's step interval shrinks from the base tick as the run goes on
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpeedCurve {
+    pub by: SpeedUp,
+    /// Seconds taken off the interval per [SpeedUp] unit
+    pub step: f32,
+    /// The interval never drops below this, unless the base tick already does, nor below [MIN_STEP_SECONDS]
+    pub min_seconds: f32,
+}
+
+impl Default for SpeedCurve {
+    fn default() -> Self {
+        SpeedCurve {
+            by: SpeedUp::Never,
+            step: 0.01,
+            min_seconds: 0.08,
+        }
+    }
+}
+
+impl SpeedCurve {
+    /// Seconds until the next step of a snake that has eaten `food`, grown by `grown` segments,
+    /// and been going for `elapsed` seconds
+    pub fn step_seconds(&self, base: f32, food: u32, grown: usize, elapsed: f32) -> f32 {
+        let units = match self.by {
+            SpeedUp::Never => 0.0,
+            SpeedUp::PerFood => food as f32,
+            SpeedUp::PerLength => grown as f32,
+            SpeedUp::PerSecond => elapsed,
+        };
+        (base - self.step * units).max(self.min_seconds.min(base)).max(MIN_STEP_SECONDS)
+    }
+}
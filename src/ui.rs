@@ -0,0 +1,37 @@
+//! Helpers shared by the UI overlays
+use bevy::prelude::*;
+
+/// Colour for whatever the player should notice: the selected menu entry, a new record, a win
+pub const HIGHLIGHT_COLOR: Color = Color::rgb(1.0, 0.84, 0.0);
+
+/// Absolutely positioned text, which is what every overlay in the game is built from
+pub fn text(
+    font: Handle<Font>,
+    value: impl Into<String>,
+    font_size: f32,
+    color: Color,
+    position: Rect<Val>,
+) -> TextComponents {
+    TextComponents {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position,
+            ..Default::default()
+        },
+        text: Text {
+            value: value.into(),
+            font,
+            style: TextStyle { font_size, color },
+        },
+        ..Default::default()
+    }
+}
+
+/// Position `left`/`top` pixels from the top left corner
+pub fn top_left(left: f32, top: f32) -> Rect<Val> {
+    Rect {
+        left: Val::Px(left),
+        top: Val::Px(top),
+        ..Default::default()
+    }
+}